@@ -0,0 +1,100 @@
+//! Compares how fast the interpreter, the compiled form and its superinstructions run the example
+//! programs. Run with `cargo bench`, an argument only runs the programs whose name contains it.
+
+extern crate befreak;
+
+use std::env;
+use std::time::{Duration, Instant};
+
+use befreak::compiled::CompiledProgram;
+use befreak::interpreter::{self, Limits, Outcome};
+use befreak::{InstructionExecutionStatus, MemoryIo, Program, State};
+
+/// The example programs, with the number of steps to run them for: the primes programs never halt.
+const PROGRAMS: &[(&str, &str, usize)] = &[
+    ("hello", include_str!("../programs/hello"), 1),
+    ("hello2", include_str!("../programs/hello2"), 1),
+    ("hello3", include_str!("../programs/hello3"), 1),
+    ("primes", include_str!("../programs/primes"), 20_000_000),
+    ("primes2", include_str!("../programs/primes2"), 20_000_000),
+    ("primes3", include_str!("../programs/primes3"), 20_000_000),
+];
+
+/// Run the hello programs at least this long, over and over.
+const MINIMUM: Duration = Duration::from_millis(200);
+
+fn state(source: &str) -> State<MemoryIo> {
+    let program: Program = source.parse().expect("valid program");
+    State::with_io(program, MemoryIo::default())
+}
+
+/// Time `run` on fresh states until `MINIMUM` has passed, returning the time per step in ns.
+fn measure<F: FnMut(&mut State<MemoryIo>) -> usize>(source: &str, mut run: F) -> f64 {
+    let mut steps = 0;
+    let mut elapsed = Duration::from_secs(0);
+    while elapsed < MINIMUM {
+        let mut state = state(source);
+        let start = Instant::now();
+        steps += run(&mut state);
+        elapsed += start.elapsed();
+    }
+    elapsed.as_secs_f64() * 1e9 / steps as f64
+}
+
+fn steps(outcome: Outcome) -> usize {
+    match outcome {
+        Outcome::Halted { steps } | Outcome::StepLimit { steps } => steps,
+        outcome => panic!("unexpected {:?}", outcome),
+    }
+}
+
+fn main() {
+    let filter = env::args().skip(1).find(|arg| !arg.starts_with('-'));
+    println!(
+        "{:<10} {:>12} {:>12} {:>12} {:>12} {:>8}",
+        "program", "steps", "interpreter", "compiled", "fused", "speedup"
+    );
+    for &(name, source, max_steps) in PROGRAMS {
+        if filter
+            .as_ref()
+            .is_some_and(|filter| !name.contains(filter.as_str()))
+        {
+            continue;
+        }
+        let limits = Limits {
+            max_steps: Some(max_steps).filter(|_| max_steps > 1),
+            ..Limits::default()
+        };
+        let compiled = CompiledProgram::new(&state(source).program);
+
+        let mut count = 0;
+        let interpreted = measure(source, |state| {
+            count = steps(interpreter::run_with_limits(state, &mut (), &limits).expect("runs"));
+            count
+        });
+        let stepped = measure(source, |state| {
+            let mut steps = 0;
+            while limits.max_steps.is_none_or(|max| steps < max) {
+                match compiled.step(state).expect("runs") {
+                    InstructionExecutionStatus::Successful(_) => steps += 1,
+                    InstructionExecutionStatus::Halt => break,
+                }
+            }
+            steps
+        });
+        let fused = measure(source, |state| {
+            let max = limits.max_steps.unwrap_or(usize::MAX);
+            steps(compiled.run_with_max_steps(state, max).expect("runs"))
+        });
+
+        println!(
+            "{:<10} {:>12} {:>9.2} ns {:>9.2} ns {:>9.2} ns {:>7.1}x",
+            name,
+            count,
+            interpreted,
+            stepped,
+            fused,
+            interpreted / fused
+        );
+    }
+}
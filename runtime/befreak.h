@@ -0,0 +1,479 @@
+/* The runtime of a Befreak program translated to C by `befreak compile`.
+ *
+ * Translations are C99 and define `befreak_cell`, `befreak_ucell`, its unsigned counterpart,
+ * `BEFREAK_CELL_BITS`, `BEFREAK_CELL_FORMAT` and `BEFREAK_RECORD_OUTPUT` before this runtime.
+ * Arithmetic is done unsigned so that it wraps around like it does in `befreak`. The output is only
+ * recorded, to unwrite it in reverse mode, if `BEFREAK_RECORD_OUTPUT` is 1, when the program has a
+ * reachable unwrite.
+ *
+ * Runtime errors are reported like `befreak` reports them, with exit status 1. `--max-steps N`
+ * stops the program after N steps with exit status 2.
+ *
+ * The stacks have a fixed capacity, exceeding it is an error. It is `BEFREAK_STACK_SIZE` elements
+ * unless `BEFREAK_DATA_STACK_SIZE`, `BEFREAK_CONTROL_STACK_SIZE`, `BEFREAK_INPUT_STACK_SIZE` or
+ * `BEFREAK_OUTPUT_STACK_SIZE` sets it for one stack.
+ *
+ * Without stdio, on a microcontroller say, define `BEFREAK_GETCHAR()` and `BEFREAK_PUTCHAR(c)` to
+ * read and write bytes, returning a negative number at the end of the input or on an error, and
+ * `BEFREAK_FAIL(status, message)` to handle an error, where `message` is the error without the
+ * `error: ` prefix and `status` the exit status `befreak` would have. `BEFREAK_FAIL` must not
+ * return. Define `BEFREAK_NO_MAIN` to leave out `main`, to include the translation in a program of
+ * your own that calls `befreak_run(&befreak_machine)`. Only the defaults use the hosted part of
+ * the C library.
+ */
+
+#include <stdarg.h>
+#include <stddef.h>
+
+#ifndef BEFREAK_STACK_SIZE
+#define BEFREAK_STACK_SIZE 4096
+#endif
+
+#ifndef BEFREAK_DATA_STACK_SIZE
+#define BEFREAK_DATA_STACK_SIZE BEFREAK_STACK_SIZE
+#endif
+
+#ifndef BEFREAK_CONTROL_STACK_SIZE
+#define BEFREAK_CONTROL_STACK_SIZE BEFREAK_STACK_SIZE
+#endif
+
+#ifndef BEFREAK_INPUT_STACK_SIZE
+#define BEFREAK_INPUT_STACK_SIZE BEFREAK_STACK_SIZE
+#endif
+
+#ifndef BEFREAK_RECORD_OUTPUT
+#define BEFREAK_RECORD_OUTPUT 1
+#endif
+
+#ifndef BEFREAK_OUTPUT_STACK_SIZE
+#if BEFREAK_RECORD_OUTPUT
+#define BEFREAK_OUTPUT_STACK_SIZE BEFREAK_STACK_SIZE
+#else
+#define BEFREAK_OUTPUT_STACK_SIZE 1
+#endif
+#endif
+
+/* The maximum length of an error message, longer ones are cut off. */
+#define BEFREAK_MESSAGE_SIZE 256
+
+#ifndef BEFREAK_GETCHAR
+#define BEFREAK_GETCHAR() getchar()
+#define BEFREAK_STDIN
+#endif
+
+#ifndef BEFREAK_PUTCHAR
+#define BEFREAK_PUTCHAR(c) putchar(c)
+#define BEFREAK_STDOUT
+#endif
+
+#ifndef BEFREAK_FAIL
+#define BEFREAK_FAIL(status, message) befreak_report(status, message)
+#define BEFREAK_STDERR
+#endif
+
+#if defined(BEFREAK_STDIN) || defined(BEFREAK_STDOUT)
+#include <errno.h>
+#include <string.h>
+#endif
+
+#if defined(BEFREAK_STDIN) || defined(BEFREAK_STDOUT) || defined(BEFREAK_STDERR) || \
+    !defined(BEFREAK_NO_MAIN)
+#include <stdio.h>
+#include <stdlib.h>
+#endif
+
+#if !defined(BEFREAK_NO_MAIN)
+#include <string.h>
+#endif
+
+#if defined(__GNUC__)
+#define BEFREAK_NORETURN __attribute__((noreturn))
+#define BEFREAK_UNUSED __attribute__((unused))
+#else
+#define BEFREAK_NORETURN
+#define BEFREAK_UNUSED
+#endif
+
+/* A binary operation on cells that wraps around on overflow. */
+#define BEFREAK_WRAP(a, op, b) ((befreak_cell)((befreak_ucell)(a) op (befreak_ucell)(b)))
+
+struct befreak_stack {
+    const char *name;
+    befreak_cell *items;
+    size_t size;
+    size_t len;
+};
+
+struct befreak_machine {
+    struct befreak_stack data;
+    struct befreak_stack control;
+    /* Characters unread in reverse mode, they are read again before new input. */
+    struct befreak_stack input;
+    /* Characters written, to unwrite them in reverse mode. */
+    struct befreak_stack output;
+    /* The number being read, 10 to the power of its number of digits, and that number of digits. */
+    befreak_ucell number;
+    befreak_ucell scale;
+    size_t digits;
+    unsigned long long steps;
+    unsigned long long max_steps;
+};
+
+struct befreak_message {
+    char text[BEFREAK_MESSAGE_SIZE];
+    size_t len;
+};
+
+static void befreak_append(struct befreak_message *message, char c)
+{
+    if (message->len + 1 < BEFREAK_MESSAGE_SIZE) {
+        message->text[message->len++] = c;
+        message->text[message->len] = '\0';
+    }
+}
+
+/* Append `n` in base `base`, with a minus sign if `negative`, padded to `width` with `pad`. */
+static void befreak_append_number(struct befreak_message *message, unsigned long long n,
+                                  int negative, unsigned base, int width, char pad)
+{
+    char digits[24];
+    int len = 0;
+    do {
+        digits[len++] = "0123456789abcdef"[n % base];
+        n /= base;
+    } while (n > 0);
+    if (negative) {
+        befreak_append(message, '-');
+    }
+    for (; width > len; width--) {
+        befreak_append(message, pad);
+    }
+    while (len > 0) {
+        befreak_append(message, digits[--len]);
+    }
+}
+
+/* Append `format` like vsprintf does. Only what the runtime needs is supported, so that failing
+ * does not need stdio: `%s`, `%%`, and `%d`, `%u` and `%x` with zero padding, a width and an `l`
+ * or `ll` length. */
+static void befreak_format(struct befreak_message *message, const char *format, va_list arguments)
+{
+    for (; *format != '\0'; format++) {
+        char pad = ' ';
+        int width = 0;
+        int longs = 0;
+        if (*format != '%') {
+            befreak_append(message, *format);
+            continue;
+        }
+        format++;
+        if (*format == '0') {
+            pad = '0';
+            format++;
+        }
+        for (; *format >= '0' && *format <= '9'; format++) {
+            width = width * 10 + (*format - '0');
+        }
+        for (; *format == 'l'; format++) {
+            longs++;
+        }
+        switch (*format) {
+        case '\0':
+            return;
+        case 's': {
+            const char *s = va_arg(arguments, const char *);
+            for (; *s != '\0'; s++) {
+                befreak_append(message, *s);
+            }
+            break;
+        }
+        case 'd': {
+            long long d = longs == 0   ? va_arg(arguments, int)
+                          : longs == 1 ? va_arg(arguments, long)
+                                       : va_arg(arguments, long long);
+            unsigned long long n = d < 0 ? 0 - (unsigned long long)d : (unsigned long long)d;
+            befreak_append_number(message, n, d < 0, 10, width, pad);
+            break;
+        }
+        case 'u':
+        case 'x': {
+            unsigned long long n = longs == 0   ? va_arg(arguments, unsigned)
+                                   : longs == 1 ? va_arg(arguments, unsigned long)
+                                                : va_arg(arguments, unsigned long long);
+            befreak_append_number(message, n, 0, *format == 'u' ? 10 : 16, width, pad);
+            break;
+        }
+        default:
+            befreak_append(message, *format);
+        }
+    }
+}
+
+#ifdef BEFREAK_STDERR
+BEFREAK_NORETURN
+static void befreak_report(int status, const char *message)
+{
+#ifdef BEFREAK_STDOUT
+    fflush(stdout);
+#endif
+    fprintf(stderr, "error: %s\n", message);
+    exit(status);
+}
+#endif
+
+/* Stop with exit status `status` and the error described by `format`, followed by `at`. */
+BEFREAK_NORETURN
+static void befreak_exit(int status, const char *at, const char *format, va_list arguments)
+{
+    struct befreak_message message;
+    message.text[0] = '\0';
+    message.len = 0;
+    befreak_format(&message, format, arguments);
+    for (; *at != '\0'; at++) {
+        befreak_append(&message, *at);
+    }
+    BEFREAK_FAIL(status, message.text);
+    /* in case the hook does return after all */
+    for (;;) {
+    }
+}
+
+/* Stop with the error described by `format`, followed by `at`, where it happened. */
+BEFREAK_NORETURN BEFREAK_UNUSED
+static void befreak_fail(const char *at, const char *format, ...)
+{
+    va_list arguments;
+    va_start(arguments, format);
+    befreak_exit(1, at, format, arguments);
+}
+
+/* Stop the program at the step limit, with exit status 2. */
+BEFREAK_NORETURN
+static void befreak_step_limit(const char *format, ...)
+{
+    va_list arguments;
+    va_start(arguments, format);
+    befreak_exit(2, "", format, arguments);
+}
+
+/* Count a step, stopping the program at the step limit. */
+BEFREAK_UNUSED
+static void befreak_tick(struct befreak_machine *m)
+{
+    if (m->steps == m->max_steps) {
+        befreak_step_limit("step limit of %llu steps reached", m->steps);
+    }
+    m->steps++;
+}
+
+BEFREAK_NORETURN BEFREAK_UNUSED
+static void befreak_underflow(const char *at, const char *stack, size_t required, size_t found)
+{
+    befreak_fail(at, "stack underflow on the %s, need %lu element%s but found %lu", stack,
+                 (unsigned long)required, required == 1 ? "" : "s", (unsigned long)found);
+}
+
+/* Fail unless the data and control stacks hold at least `data` and `control` elements. */
+BEFREAK_UNUSED
+static void befreak_need(struct befreak_machine *m, size_t data, size_t control, const char *at)
+{
+    if (m->data.len < data) {
+        befreak_underflow(at, m->data.name, data, m->data.len);
+    }
+    if (m->control.len < control) {
+        befreak_underflow(at, m->control.name, control, m->control.len);
+    }
+}
+
+BEFREAK_UNUSED
+static void befreak_push(struct befreak_stack *s, befreak_cell value, const char *at)
+{
+    if (s->len == s->size) {
+        befreak_fail(at, "stack overflow on the %s, it holds at most %lu elements", s->name,
+                     (unsigned long)s->size);
+    }
+    s->items[s->len++] = value;
+}
+
+BEFREAK_UNUSED
+static befreak_cell befreak_pop(struct befreak_stack *s)
+{
+    return s->items[--s->len];
+}
+
+/* The element `n` from the top of a stack, the top is 1. */
+#define BEFREAK_AT(s, n) ((s).items[(s).len - (n)])
+
+/* Append a digit to the number being read, in front of it in reverse mode. */
+BEFREAK_UNUSED
+static void befreak_digit(struct befreak_machine *m, befreak_ucell digit, int reverse_mode)
+{
+    if (reverse_mode) {
+        m->number += digit * m->scale;
+    } else {
+        m->number = m->number * 10 + digit;
+    }
+    m->scale *= 10;
+    m->digits++;
+}
+
+/* Xor the number that was read onto the top of the data stack. */
+BEFREAK_UNUSED
+static void befreak_digit_end(struct befreak_machine *m, const char *at)
+{
+    befreak_need(m, 1, 0, at);
+    BEFREAK_AT(m->data, 1) ^= (befreak_cell)m->number;
+    m->number = 0;
+    m->scale = 1;
+    m->digits = 0;
+}
+
+BEFREAK_UNUSED
+static befreak_cell befreak_div(befreak_cell y, befreak_cell x)
+{
+    /* the quotient of the smallest value by -1 wraps around to itself */
+    return x == -1 ? BEFREAK_WRAP(0, -, y) : y / x;
+}
+
+BEFREAK_UNUSED
+static befreak_cell befreak_rem(befreak_cell y, befreak_cell x)
+{
+    return x == -1 ? 0 : y % x;
+}
+
+BEFREAK_UNUSED
+static befreak_cell befreak_rotate_left(befreak_cell y, befreak_cell x)
+{
+    unsigned n = (unsigned)((befreak_ucell)x & (BEFREAK_CELL_BITS - 1));
+    befreak_ucell u = (befreak_ucell)y;
+    return n == 0 ? y : (befreak_cell)((u << n) | (u >> (BEFREAK_CELL_BITS - n)));
+}
+
+BEFREAK_UNUSED
+static befreak_cell befreak_rotate_right(befreak_cell y, befreak_cell x)
+{
+    unsigned n = (unsigned)((befreak_ucell)x & (BEFREAK_CELL_BITS - 1));
+    befreak_ucell u = (befreak_ucell)y;
+    return n == 0 ? y : (befreak_cell)((u >> n) | (u << (BEFREAK_CELL_BITS - n)));
+}
+
+BEFREAK_UNUSED
+static void befreak_read(struct befreak_machine *m, const char *at)
+{
+    int c;
+    if (m->input.len > 0) {
+        befreak_push(&m->data, befreak_pop(&m->input), at);
+        return;
+    }
+#ifdef BEFREAK_STDOUT
+    /* make sure a prompt is visible before blocking on input */
+    if (fflush(stdout) == EOF) {
+        befreak_fail(at, "i/o error: %s", strerror(errno));
+    }
+#endif
+    c = BEFREAK_GETCHAR();
+    if (c < 0) {
+#ifdef BEFREAK_STDIN
+        if (ferror(stdin)) {
+            befreak_fail(at, "i/o error: %s", strerror(errno));
+        }
+#endif
+        befreak_fail(at, "input exhausted");
+    }
+    if (c < 0x20 || c >= 0x7f) {
+        befreak_fail(at, "read byte 0x%02x, which is not printable ASCII", (unsigned)c);
+    }
+    befreak_push(&m->data, c, at);
+}
+
+BEFREAK_UNUSED
+static void befreak_unread(struct befreak_machine *m, const char *at)
+{
+    befreak_cell top;
+    befreak_need(m, 1, 0, at);
+    top = BEFREAK_AT(m->data, 1);
+    if (top < 0x20 || top >= 0x7f) {
+        befreak_fail(at,
+                     "can not unread value %" BEFREAK_CELL_FORMAT ", which is not printable ASCII",
+                     top);
+    }
+    befreak_push(&m->input, top, at);
+    befreak_pop(&m->data);
+}
+
+BEFREAK_UNUSED
+static void befreak_write(struct befreak_machine *m, const char *at)
+{
+    befreak_cell top;
+    befreak_need(m, 1, 0, at);
+    top = BEFREAK_AT(m->data, 1);
+    if (top < 0 || top >= 128) {
+        befreak_fail(at, "can not write non-ASCII value %" BEFREAK_CELL_FORMAT, top);
+    }
+#if BEFREAK_RECORD_OUTPUT
+    befreak_push(&m->output, top, at);
+#endif
+    if (BEFREAK_PUTCHAR((int)top) < 0) {
+#ifdef BEFREAK_STDOUT
+        befreak_fail(at, "i/o error: %s", strerror(errno));
+#else
+        befreak_fail(at, "i/o error");
+#endif
+    }
+    befreak_pop(&m->data);
+}
+
+BEFREAK_UNUSED
+static void befreak_unwrite(struct befreak_machine *m, const char *at)
+{
+    if (m->output.len == 0) {
+        befreak_underflow(at, m->output.name, 1, 0);
+    }
+    befreak_push(&m->data, befreak_pop(&m->output), at);
+}
+
+static befreak_cell befreak_data[BEFREAK_DATA_STACK_SIZE];
+static befreak_cell befreak_control[BEFREAK_CONTROL_STACK_SIZE];
+static befreak_cell befreak_input[BEFREAK_INPUT_STACK_SIZE];
+static befreak_cell befreak_output[BEFREAK_OUTPUT_STACK_SIZE];
+
+static struct befreak_machine befreak_machine = {
+    {"data stack", befreak_data, BEFREAK_DATA_STACK_SIZE, 0},
+    {"control stack", befreak_control, BEFREAK_CONTROL_STACK_SIZE, 0},
+    {"input stack", befreak_input, BEFREAK_INPUT_STACK_SIZE, 0},
+    {"output stack", befreak_output, BEFREAK_OUTPUT_STACK_SIZE, 0},
+    0,
+    1,
+    0,
+    0,
+    (unsigned long long)-1,
+};
+
+static void befreak_run(struct befreak_machine *m);
+
+#ifndef BEFREAK_NO_MAIN
+int main(int argc, char **argv)
+{
+    int i;
+    for (i = 1; i < argc; i++) {
+        if (strcmp(argv[i], "--max-steps") == 0) {
+            char *end;
+            if (i + 1 == argc || *argv[i + 1] == '\0') {
+                fputs("error: --max-steps needs a number of steps\n", stderr);
+                return 2;
+            }
+            befreak_machine.max_steps = strtoull(argv[i + 1], &end, 10);
+            if (*end != '\0') {
+                fputs("error: --max-steps needs a number of steps\n", stderr);
+                return 2;
+            }
+        }
+    }
+    befreak_run(&befreak_machine);
+    if (fflush(stdout) == EOF) {
+        fprintf(stderr, "error: unable to write output: %s\n", strerror(errno));
+        return 1;
+    }
+    return 0;
+}
+#endif
@@ -0,0 +1,193 @@
+// The runtime of a Befreak program translated to Rust by `befreak compile`.
+//
+// Runtime errors are reported like `befreak` reports them, with exit status 1. `--max-steps N`
+// stops the program after N steps with exit status 2. The output is only kept, to unwrite it in
+// reverse mode, if `RECORD_OUTPUT` is set, when the program has a reachable unwrite.
+
+use std::env;
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+use std::process;
+
+struct Machine {
+    data: Vec<Cell>,
+    control: Vec<Cell>,
+    /// Characters unread in reverse mode, they are read again before new input.
+    input: Vec<u8>,
+    /// Characters written, to unwrite them in reverse mode.
+    output: Vec<u8>,
+    /// The number being read, 10 to the power of its number of digits, and that number of digits.
+    number: Cell,
+    scale: Cell,
+    digits: usize,
+    steps: u64,
+    max_steps: u64,
+    stdin: io::Stdin,
+    stdout: io::BufWriter<io::Stdout>,
+}
+
+impl Machine {
+    fn new(max_steps: u64) -> Self {
+        Machine {
+            data: Vec::new(),
+            control: Vec::new(),
+            input: Vec::new(),
+            output: Vec::new(),
+            number: 0,
+            scale: 1,
+            digits: 0,
+            steps: 0,
+            max_steps: max_steps,
+            stdin: io::stdin(),
+            stdout: io::BufWriter::new(io::stdout()),
+        }
+    }
+
+    /// Count a step, stopping the program at the step limit.
+    #[inline]
+    fn tick(&mut self) {
+        if self.steps == self.max_steps {
+            let message = format!("step limit of {} steps reached", self.steps);
+            self.exit(&message, 2);
+        }
+        self.steps += 1;
+    }
+
+    /// Flush the output and exit with an error message.
+    #[cold]
+    fn exit(&mut self, message: &str, status: i32) -> ! {
+        let _ = self.stdout.flush();
+        eprintln!("error: {}", message);
+        process::exit(status);
+    }
+
+    /// Exit with the error `kind` that happened `at` an instruction.
+    #[cold]
+    fn fail<T: Display>(&mut self, kind: T, at: &str) -> ! {
+        let message = format!("{}{}", kind, at);
+        self.exit(&message, 1)
+    }
+
+    /// Fail unless the data and control stacks hold at least `data` and `control` elements.
+    #[inline]
+    fn need(&mut self, data: usize, control: usize, at: &str) {
+        if self.data.len() < data {
+            let found = self.data.len();
+            self.fail(underflow("data stack", data, found), at);
+        }
+        if self.control.len() < control {
+            let found = self.control.len();
+            self.fail(underflow("control stack", control, found), at);
+        }
+    }
+
+    /// Append a digit to the number being read, in front of it in reverse mode.
+    #[inline]
+    fn digit(&mut self, digit: Cell, reverse_mode: bool) {
+        if reverse_mode {
+            self.number = self.number.wrapping_add(digit.wrapping_mul(self.scale));
+        } else {
+            self.number = self.number.wrapping_mul(10).wrapping_add(digit);
+        }
+        self.scale = self.scale.wrapping_mul(10);
+        self.digits += 1;
+    }
+
+    /// Xor the number that was read onto the top of the data stack.
+    fn digit_end(&mut self, at: &str) {
+        self.need(1, 0, at);
+        let top = self.data.len() - 1;
+        self.data[top] ^= self.number;
+        self.number = 0;
+        self.scale = 1;
+        self.digits = 0;
+    }
+
+    fn read(&mut self, at: &str) {
+        if let Some(c) = self.input.pop() {
+            self.data.push(Cell::from(c));
+            return;
+        }
+        // make sure a prompt is visible before blocking on input
+        if let Err(e) = self.stdout.flush() {
+            self.fail(format!("i/o error: {:?}", e.kind()), at);
+        }
+        let mut byte = [0];
+        loop {
+            match self.stdin.lock().read(&mut byte) {
+                Ok(0) => self.fail("input exhausted", at),
+                Ok(_) => break,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => self.fail(format!("i/o error: {:?}", e.kind()), at),
+            }
+        }
+        if byte[0] < 0x20 || byte[0] >= 0x7f {
+            let kind = format!("read byte {:#04x}, which is not printable ASCII", byte[0]);
+            self.fail(kind, at);
+        }
+        self.data.push(Cell::from(byte[0]));
+    }
+
+    fn unread(&mut self, at: &str) {
+        self.need(1, 0, at);
+        let top = self.data[self.data.len() - 1];
+        if top < 0x20 || top >= 0x7f {
+            let kind = format!("can not unread value {}, which is not printable ASCII", top);
+            self.fail(kind, at);
+        }
+        self.data.pop();
+        self.input.push(top as u8);
+    }
+
+    fn write(&mut self, at: &str) {
+        self.need(1, 0, at);
+        let top = self.data[self.data.len() - 1];
+        if top < 0 || top >= 128 {
+            self.fail(format!("can not write non-ASCII value {}", top), at);
+        }
+        if let Err(e) = self.stdout.write_all(&[top as u8]) {
+            self.fail(format!("i/o error: {:?}", e.kind()), at);
+        }
+        self.data.pop();
+        if RECORD_OUTPUT {
+            self.output.push(top as u8);
+        }
+    }
+
+    fn unwrite(&mut self, at: &str) {
+        match self.output.pop() {
+            Some(c) => self.data.push(Cell::from(c)),
+            None => self.fail(underflow("output stack", 1, 0), at),
+        }
+    }
+}
+
+fn underflow(stack: &str, required: usize, found: usize) -> String {
+    format!(
+        "stack underflow on the {}, need {} element{} but found {}",
+        stack,
+        required,
+        if required == 1 { "" } else { "s" },
+        found
+    )
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let max_steps = match args.iter().position(|arg| arg == "--max-steps") {
+        Some(i) => match args.get(i + 1).and_then(|n| n.parse().ok()) {
+            Some(n) => n,
+            None => {
+                eprintln!("error: --max-steps needs a number of steps");
+                process::exit(2);
+            }
+        },
+        None => u64::max_value(),
+    };
+    let mut machine = Machine::new(max_steps);
+    run(&mut machine);
+    if let Err(e) = machine.stdout.flush() {
+        eprintln!("error: unable to write output: {}", e);
+        process::exit(1);
+    }
+}
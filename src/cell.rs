@@ -0,0 +1,232 @@
+//! The values stored on the data and control stacks.
+//!
+//! Befreak works with signed integers. Every operation is defined for every input, so the bounded
+//! cell types wrap around on overflow in both debug and release builds. The reversible pairs of
+//! operations stay each other's inverse under this wrapping behaviour.
+//!
+//! With the `bignum` feature `BigInt` can be used as an unbounded cell type. All arithmetic and
+//! bitwise operations are exact, with the bitwise operations acting on the infinite two's
+//! complement representation. Rotation has no meaning without a bit width, so it is not supported.
+
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
+#[cfg(feature = "bignum")]
+use num_traits::{One, ToPrimitive, Zero};
+
+/// A value on the data or control stack.
+pub trait Cell: Clone + Ord + Hash + Debug + Display {
+    fn zero() -> Self;
+
+    fn one() -> Self;
+
+    fn is_zero(&self) -> bool;
+
+    /// The ASCII value of `c`.
+    fn from_char(c: char) -> Self;
+
+    /// The ASCII character with this value, if there is one.
+    fn to_char(&self) -> Option<char>;
+
+    /// The number written in decimal in `digits`, which only contains ASCII digits.
+    fn from_digits(digits: &str) -> Self;
+
+    fn increment(&self) -> Self;
+
+    fn decrement(&self) -> Self;
+
+    fn add(&self, other: &Self) -> Self;
+
+    fn subtract(&self, other: &Self) -> Self;
+
+    /// The quotient and remainder of dividing by `divisor`, which is not zero.
+    ///
+    /// The quotient is rounded towards zero, so that `multiply_add` is the inverse of this.
+    fn div_rem(&self, divisor: &Self) -> (Self, Self);
+
+    /// `self * factor + addend`.
+    fn multiply_add(&self, factor: &Self, addend: &Self) -> Self;
+
+    fn not(&self) -> Self;
+
+    fn and(&self, other: &Self) -> Self;
+
+    fn or(&self, other: &Self) -> Self;
+
+    fn xor(&self, other: &Self) -> Self;
+
+    /// Rotate the bits left by `n` modulo the number of bits, `None` if the cell is unbounded.
+    fn rotate_left(&self, n: &Self) -> Option<Self>;
+
+    /// Rotate the bits right by `n` modulo the number of bits, `None` if the cell is unbounded.
+    fn rotate_right(&self, n: &Self) -> Option<Self>;
+}
+
+macro_rules! impl_wrapping_cell {
+    ($t:ty) => {
+        impl Cell for $t {
+            fn zero() -> Self {
+                0
+            }
+
+            fn one() -> Self {
+                1
+            }
+
+            fn is_zero(&self) -> bool {
+                *self == 0
+            }
+
+            fn from_char(c: char) -> Self {
+                debug_assert!(c.is_ascii());
+                c as $t
+            }
+
+            fn to_char(&self) -> Option<char> {
+                if 0 <= *self && *self < 128 {
+                    Some(*self as u8 as char)
+                } else {
+                    None
+                }
+            }
+
+            fn from_digits(digits: &str) -> Self {
+                digits.bytes().fold(0, |n: $t, digit| {
+                    debug_assert!(digit.is_ascii_digit());
+                    n.wrapping_mul(10).wrapping_add(<$t>::from(digit - b'0'))
+                })
+            }
+
+            fn increment(&self) -> Self {
+                self.wrapping_add(1)
+            }
+
+            fn decrement(&self) -> Self {
+                self.wrapping_sub(1)
+            }
+
+            fn add(&self, other: &Self) -> Self {
+                self.wrapping_add(*other)
+            }
+
+            fn subtract(&self, other: &Self) -> Self {
+                self.wrapping_sub(*other)
+            }
+
+            fn div_rem(&self, divisor: &Self) -> (Self, Self) {
+                (self.wrapping_div(*divisor), self.wrapping_rem(*divisor))
+            }
+
+            fn multiply_add(&self, factor: &Self, addend: &Self) -> Self {
+                self.wrapping_mul(*factor).wrapping_add(*addend)
+            }
+
+            fn not(&self) -> Self {
+                !*self
+            }
+
+            fn and(&self, other: &Self) -> Self {
+                *self & *other
+            }
+
+            fn or(&self, other: &Self) -> Self {
+                *self | *other
+            }
+
+            fn xor(&self, other: &Self) -> Self {
+                *self ^ *other
+            }
+
+            fn rotate_left(&self, n: &Self) -> Option<Self> {
+                // the truncating cast keeps n modulo the number of bits intact
+                Some(<$t>::rotate_left(*self, *n as u32))
+            }
+
+            fn rotate_right(&self, n: &Self) -> Option<Self> {
+                Some(<$t>::rotate_right(*self, *n as u32))
+            }
+        }
+    };
+}
+
+impl_wrapping_cell!(i32);
+impl_wrapping_cell!(i64);
+
+#[cfg(feature = "bignum")]
+impl Cell for BigInt {
+    fn zero() -> Self {
+        Zero::zero()
+    }
+
+    fn one() -> Self {
+        One::one()
+    }
+
+    fn is_zero(&self) -> bool {
+        Zero::is_zero(self)
+    }
+
+    fn from_char(c: char) -> Self {
+        debug_assert!(c.is_ascii());
+        BigInt::from(c as u8)
+    }
+
+    fn to_char(&self) -> Option<char> {
+        self.to_u8().filter(|&b| b < 128).map(char::from)
+    }
+
+    fn from_digits(digits: &str) -> Self {
+        digits.parse().expect("should only be digits")
+    }
+
+    fn increment(&self) -> Self {
+        self + 1
+    }
+
+    fn decrement(&self) -> Self {
+        self - 1
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn subtract(&self, other: &Self) -> Self {
+        self - other
+    }
+
+    fn div_rem(&self, divisor: &Self) -> (Self, Self) {
+        // `/` and `%` round towards zero, like the bounded cells
+        (self / divisor, self % divisor)
+    }
+
+    fn multiply_add(&self, factor: &Self, addend: &Self) -> Self {
+        self * factor + addend
+    }
+
+    fn not(&self) -> Self {
+        !self
+    }
+
+    fn and(&self, other: &Self) -> Self {
+        self & other
+    }
+
+    fn or(&self, other: &Self) -> Self {
+        self | other
+    }
+
+    fn xor(&self, other: &Self) -> Self {
+        self ^ other
+    }
+
+    fn rotate_left(&self, _n: &Self) -> Option<Self> {
+        None
+    }
+
+    fn rotate_right(&self, _n: &Self) -> Option<Self> {
+        None
+    }
+}
@@ -0,0 +1,240 @@
+//! The control-flow graph of a program, to review the structure of large programs.
+//!
+//! A block is a maximal run of instructions the instruction pointer executes one after the other
+//! without a choice: it ends at a branch, a mirror or `?`, where a run halts or is stuck, or before
+//! an instruction that can also be reached from elsewhere. Blocks are made of pointers rather than
+//! cells, so a cell passed in two directions or modes belongs to two blocks. Only the part of the
+//! program reachable from the start is included.
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use direction::Direction;
+use instruction::Instruction;
+use pointer::{Flow, Pointer};
+use program::Program;
+use reachability::Reachability;
+
+/// What an edge does to the control stack.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Effect {
+    /// Nothing, the edge is not a branch.
+    Nothing,
+    /// `branch_1` pushes the turn taken, 0 or 1.
+    Push(u8),
+    /// `branch_2` pops a zero.
+    PopZero,
+    /// `branch_2` pops a value other than zero.
+    PopNonZero,
+    /// `branch_3` toggles the top of the control stack.
+    Toggle,
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Effect::Nothing => Ok(()),
+            Effect::Push(value) => write!(f, "push {}", value),
+            Effect::PopZero => write!(f, "pop 0"),
+            Effect::PopNonZero => write!(f, "pop non-zero"),
+            Effect::Toggle => write!(f, "toggle"),
+        }
+    }
+}
+
+/// How a block ends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum End {
+    /// Execution continues along the edges leaving the block.
+    Continue,
+    /// The last instruction halts.
+    Halt,
+    /// No rule can apply to the last instruction.
+    Stuck,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    /// The pointer before every instruction of the block, in the order they are executed.
+    pub pointers: Vec<Pointer>,
+    pub instructions: Vec<Instruction>,
+    pub end: End,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Edge {
+    /// The indices of the blocks in `ControlFlowGraph::blocks`.
+    pub from: usize,
+    pub to: usize,
+    /// The direction the instruction pointer moves in when it enters `to`.
+    pub direction: Direction,
+    pub effect: Effect,
+}
+
+/// The control-flow graph of a program, the first block is the one at the start.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<Block>,
+    pub edges: Vec<Edge>,
+}
+
+fn successors(flow: Flow) -> Vec<Pointer> {
+    match flow {
+        Flow::Next(next) => vec![next],
+        Flow::Branch { zero, nonzero } => vec![zero, nonzero],
+        Flow::Halt | Flow::Stuck => vec![],
+    }
+}
+
+fn instruction(program: &Program, pointer: Pointer) -> Instruction {
+    *program
+        .instruction_at(pointer.location)
+        .expect("pointer should be inside the program")
+}
+
+/// What following the edge from `pointer` to `next` does to the control stack.
+fn effect(program: &Program, pointer: Pointer, next: Pointer) -> Effect {
+    let branch = match instruction(program, pointer).direction() {
+        Some(branch) if !pointer.string_mode => branch,
+        _ => return Effect::Nothing,
+    };
+    if let Flow::Branch { zero, .. } = pointer.flow(program) {
+        if next == zero {
+            Effect::PopZero
+        } else {
+            Effect::PopNonZero
+        }
+    } else if branch == pointer.direction {
+        Effect::Toggle
+    } else {
+        // a right turn pushes 1, in reverse mode 0
+        let right = branch == pointer.direction.right();
+        Effect::Push((right != pointer.reverse_mode) as u8)
+    }
+}
+
+impl ControlFlowGraph {
+    pub fn new(program: &Program) -> Self {
+        let start = Pointer::start(program);
+        let pointers = Reachability::analyse(program).pointers;
+        let is_control_flow =
+            |p: Pointer| !p.string_mode && instruction(program, p).is_control_flow();
+
+        // a block starts at the start, after a control-flow instruction, and where paths join
+        let mut predecessors: BTreeMap<Pointer, usize> = BTreeMap::new();
+        let mut leaders = BTreeSet::new();
+        leaders.insert(start);
+        for &pointer in &pointers {
+            for next in successors(pointer.flow(program)) {
+                *predecessors.entry(next).or_insert(0) += 1;
+                if is_control_flow(pointer) {
+                    leaders.insert(next);
+                }
+            }
+        }
+        leaders.extend(
+            predecessors
+                .iter()
+                .filter(|&(_, &n)| n > 1)
+                .map(|(&pointer, _)| pointer),
+        );
+
+        // number the blocks in the order they are found from the start
+        let mut order = vec![start];
+        let mut index = BTreeMap::new();
+        index.insert(start, 0);
+        let mut blocks = Vec::new();
+        let mut exits = Vec::new();
+        while let Some(&leader) = order.get(blocks.len()) {
+            let mut block = vec![leader];
+            let mut pointer = leader;
+            let (end, next) = loop {
+                match pointer.flow(program) {
+                    Flow::Halt => break (End::Halt, vec![]),
+                    Flow::Stuck => break (End::Stuck, vec![]),
+                    flow => {
+                        let next = successors(flow);
+                        if is_control_flow(pointer) || leaders.contains(&next[0]) {
+                            break (End::Continue, next);
+                        }
+                        pointer = next[0];
+                        block.push(pointer);
+                    }
+                }
+            };
+            for &next in &next {
+                if let Entry::Vacant(entry) = index.entry(next) {
+                    entry.insert(order.len());
+                    order.push(next);
+                }
+            }
+            exits.push((blocks.len(), pointer, next));
+            blocks.push(Block {
+                instructions: block.iter().map(|&p| instruction(program, p)).collect(),
+                pointers: block,
+                end,
+            });
+        }
+
+        let edges = exits
+            .into_iter()
+            .flat_map(|(from, last, next)| {
+                let index = &index;
+                next.into_iter().map(move |next| Edge {
+                    from,
+                    to: index[&next],
+                    direction: next.direction,
+                    effect: effect(program, last, next),
+                })
+            })
+            .collect();
+
+        ControlFlowGraph { blocks, edges }
+    }
+
+    /// The graph in the Graphviz DOT language.
+    ///
+    /// Blocks are labelled with the position and direction of their first instruction and their
+    /// instructions. Blocks executed in reverse mode are dashed, blocks that halt have a double
+    /// border and blocks that are stuck are red.
+    pub fn dot(&self) -> String {
+        let mut s = String::from("digraph cfg {\n    node [shape=box, fontname=monospace];\n");
+        s.push_str("    start [shape=point];\n    start -> b0;\n");
+        for (i, block) in self.blocks.iter().enumerate() {
+            let first = block.pointers[0];
+            let instructions: String = block.instructions.iter().map(|i| i.to_string()).collect();
+            let mut attributes = format!(
+                "label=\"line {}, col {} {}\\n{}\"",
+                first.location.1 + 1,
+                first.location.0 + 1,
+                first.direction,
+                escape(&instructions)
+            );
+            if first.reverse_mode {
+                attributes.push_str(", style=dashed");
+            }
+            match block.end {
+                End::Continue => {}
+                End::Halt => attributes.push_str(", peripheries=2"),
+                End::Stuck => attributes.push_str(", color=red"),
+            }
+            s.push_str(&format!("    b{} [{}];\n", i, attributes));
+        }
+        for edge in &self.edges {
+            s.push_str(&format!(
+                "    b{} -> b{} [label=\"{}\"];\n",
+                edge.from,
+                edge.to,
+                format!("{} {}", edge.direction, edge.effect).trim_end()
+            ));
+        }
+        s.push_str("}\n");
+        s
+    }
+}
+
+/// Escape `s` for a quoted DOT string.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,622 @@
+//! A pre-decoded form of a program, for running it fast.
+//!
+//! `Instruction::execute` finds the rule to apply by testing the guards of the rules one after the
+//! other, re-testing the modes and the accumulator for every instruction. A `CompiledProgram`
+//! decodes every cell once into an opcode for forward and one for reverse mode, and stores the
+//! index of its neighbour in every direction. A step then dispatches on the opcode and mode
+//! directly.
+//!
+//! The common rules are applied here. Numbers, input and output, rotation, halting and every step
+//! that would get stuck are handed to `Instruction::execute`, so the results are always exactly
+//! those of the interpreter.
+//!
+//! `run` and `run_with_max_steps` go further and execute whole traces of superinstructions at
+//! once, see the `superinstruction` module.
+
+use cell::Cell;
+use direction::Direction;
+use instruction::{ExecutionError, InstructionExecutionStatus};
+use interpreter::Outcome;
+use io::Io;
+use pointer::Pointer;
+use program::Program;
+use rule::Rule;
+use state::State;
+use superinstruction::{self, Fused, Trace};
+
+/// A decoded instruction: what it does outside string mode, in one direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Opcode {
+    Nop,
+    Push,
+    Pop,
+    Transfer1,
+    Transfer2,
+    Interchange,
+    Increment,
+    Decrement,
+    Add,
+    Subtract,
+    Divide,
+    Multiply,
+    Not,
+    And,
+    Or,
+    Xor,
+    Toggle,
+    Equal,
+    Less,
+    Greater,
+    Swap1,
+    Swap2,
+    Swap3,
+    Dig,
+    Bury,
+    Over,
+    Under,
+    Duplicate,
+    Unduplicate,
+    StringToggle,
+    Reverse,
+    Mirror1,
+    Mirror2,
+    Branch(Direction),
+    /// `r`, which reads in forward mode and unreads in reverse mode.
+    Read,
+    /// `w`, which writes in forward mode and unwrites in reverse mode.
+    Write,
+    RotateLeft,
+    RotateRight,
+    /// Numbers, halting and unknown instructions.
+    Interpret,
+}
+
+#[derive(Clone, Debug)]
+struct Decoded {
+    /// The opcode outside string mode, in forward and in reverse mode.
+    opcodes: [Opcode; 2],
+    /// The character pushed or popped in string mode.
+    character: char,
+    /// The index of the neighbouring cell in every direction, wrapping around the edges.
+    next: [usize; 4],
+}
+
+/// A program decoded for fast execution on cells of type `C`, see the module documentation.
+#[derive(Clone, Debug)]
+pub struct CompiledProgram<C: Cell = i32> {
+    cols: usize,
+    cells: Vec<Decoded>,
+    /// The (x, y) location of every index.
+    locations: Vec<(usize, usize)>,
+    traces: Vec<Trace<C>>,
+    /// The index in `traces` of the trace starting at every slot, see `slot`.
+    starts: Vec<Option<u32>>,
+    /// The index of the trace starting where every trace ends, if it does not end in `branch_2`.
+    following: Vec<Option<Option<u32>>>,
+}
+
+fn index(direction: Direction) -> usize {
+    match direction {
+        Direction::North => 0,
+        Direction::East => 1,
+        Direction::South => 2,
+        Direction::West => 3,
+    }
+}
+
+/// The index of a pointer at the cell with index `i` among all pointers of a program.
+fn slot(i: usize, pointer: Pointer) -> usize {
+    i * 16
+        + index(pointer.direction) * 4
+        + pointer.reverse_mode as usize * 2
+        + pointer.string_mode as usize
+}
+
+impl<C: Cell> CompiledProgram<C> {
+    pub fn new(program: &Program) -> Self {
+        let (rows, cols) = (program.rows(), program.cols());
+        let mut cells = Vec::with_capacity(rows * cols);
+        let mut locations = Vec::with_capacity(rows * cols);
+        for y in 0..rows {
+            for x in 0..cols {
+                let instruction = *program
+                    .instruction_at((x, y))
+                    .expect("location inside the program");
+                locations.push((x, y));
+                cells.push(Decoded {
+                    opcodes: [instruction.opcode(), instruction.inv().opcode()],
+                    character: instruction
+                        .to_string()
+                        .chars()
+                        .next()
+                        .expect("one character"),
+                    next: [
+                        ((y + rows - 1) % rows) * cols + x,
+                        y * cols + (x + 1) % cols,
+                        ((y + 1) % rows) * cols + x,
+                        y * cols + (x + cols - 1) % cols,
+                    ],
+                });
+            }
+        }
+        let mut traces = Vec::new();
+        let mut starts = vec![None; cells.len() * 16];
+        for (pointer, trace) in superinstruction::traces(program) {
+            let (x, y) = pointer.location;
+            starts[slot(y * cols + x, pointer)] = Some(traces.len() as u32);
+            traces.push(trace);
+        }
+        let following = traces
+            .iter()
+            .map(|trace| {
+                let last = trace.operations.last().expect("traces are not empty");
+                last.end.map(|end| {
+                    let (x, y) = end.location;
+                    starts[slot(y * cols + x, end)]
+                })
+            })
+            .collect();
+        CompiledProgram {
+            cols,
+            cells,
+            locations,
+            traces,
+            starts,
+            following,
+        }
+    }
+
+    /// The trace of superinstructions `run` executes from `pointer`, if there is one.
+    pub fn trace(&self, pointer: Pointer) -> Option<&Trace<C>> {
+        self.start(pointer).map(|t| &self.traces[t as usize])
+    }
+
+    /// Execute the instruction at the current location of `state`, exactly like `State::step`.
+    ///
+    /// `state` must run the program this was compiled from.
+    pub fn step<I: Io>(
+        &self,
+        state: &mut State<I, C>,
+    ) -> Result<InstructionExecutionStatus, ExecutionError<C>> {
+        let (x, y) = state.location;
+        let i = y * self.cols + x;
+        match self.apply(i, state) {
+            Some(rule) => Ok(InstructionExecutionStatus::Successful(rule)),
+            None => state.instr().execute(state),
+        }
+    }
+
+    /// Run `state` until the program halts, like `interpreter::run` without an observer. Returns
+    /// the number of steps taken.
+    pub fn run<I: Io>(&self, state: &mut State<I, C>) -> Result<usize, ExecutionError<C>> {
+        let mut steps = 0;
+        loop {
+            steps += self.run_traces(state, usize::MAX - steps);
+            match self.step(state)? {
+                InstructionExecutionStatus::Successful(_) => steps += 1,
+                InstructionExecutionStatus::Halt => return Ok(steps),
+            }
+        }
+    }
+
+    /// Run `state` until the program halts or `max_steps` steps are taken, like
+    /// `interpreter::run_with_limits` with only `Limits::max_steps` set.
+    pub fn run_with_max_steps<I: Io>(
+        &self,
+        state: &mut State<I, C>,
+        max_steps: usize,
+    ) -> Result<Outcome, ExecutionError<C>> {
+        let mut steps = 0;
+        loop {
+            steps += self.run_traces(state, max_steps - steps);
+            if steps == max_steps {
+                return Ok(Outcome::StepLimit { steps });
+            }
+            match self.step(state)? {
+                InstructionExecutionStatus::Successful(_) => steps += 1,
+                InstructionExecutionStatus::Halt => return Ok(Outcome::Halted { steps }),
+            }
+        }
+    }
+
+    /// Execute the trace starting at the pointer of `state`, then the one starting where it ends
+    /// and so on, as long as there is one and it takes at most the `max_steps` steps left. Returns
+    /// the number of steps taken.
+    fn run_traces<I: Io>(&self, state: &mut State<I, C>, max_steps: usize) -> usize {
+        if !state.multi_digit_accumulator.is_empty() {
+            return 0;
+        }
+        let mut steps = 0;
+        let mut next = self.start(Pointer::of(state));
+        while let Some(t) = next {
+            let trace = &self.traces[t as usize];
+            if trace.steps > max_steps - steps {
+                break;
+            }
+            match self.run_trace(trace, state) {
+                Ok(taken) => steps += taken,
+                Err(taken) => return steps + taken,
+            }
+            // a trace ending in branch_2 has left the pointer in `state`
+            next = match self.following[t as usize] {
+                Some(following) => following,
+                None => self.start(Pointer::of(state)),
+            };
+        }
+        steps
+    }
+
+    /// Execute `trace`, which starts at the pointer of `state`. Returns the number of steps taken,
+    /// or if an operation does not apply the number of steps taken before it: `state` is then left
+    /// before that operation.
+    ///
+    /// The pointer in `state` is only brought up to date where it matters: before a branch, and
+    /// at the end.
+    fn run_trace<I: Io>(&self, trace: &Trace<C>, state: &mut State<I, C>) -> Result<usize, usize> {
+        let mut pointer = None;
+        let mut steps = 0;
+        for operation in &trace.operations {
+            let data = &mut state.data_stack;
+            let applies = match operation.fused {
+                Fused::Move => true,
+                Fused::Add(ref amount) => match data.pop() {
+                    Some(x) => {
+                        data.push(x.add(amount));
+                        true
+                    }
+                    None => false,
+                },
+                Fused::Xor(ref amount) => match data.pop() {
+                    Some(x) => {
+                        data.push(x.xor(amount));
+                        true
+                    }
+                    None => false,
+                },
+                Fused::PushString(ref string) => {
+                    for &c in string {
+                        data.push(C::from_char(c));
+                    }
+                    true
+                }
+                Fused::PopString(ref string) => {
+                    let matches = string.len() <= data.len()
+                        && string
+                            .iter()
+                            .enumerate()
+                            .all(|(n, &c)| data.get(data.len() - 1 - n) == Some(C::from_char(c)));
+                    if matches {
+                        for _ in string {
+                            data.pop();
+                        }
+                    }
+                    matches
+                }
+                Fused::PushControl(value) => {
+                    let value = if value == 0 { C::zero() } else { C::one() };
+                    state.control_stack.push(value);
+                    true
+                }
+                Fused::ToggleControl => match state.control_stack.pop() {
+                    Some(c) => {
+                        state.control_stack.push(c.xor(&C::one()));
+                        true
+                    }
+                    None => false,
+                },
+                Fused::Single(opcode) => {
+                    // only branches depend on the pointer
+                    if let Opcode::Branch(_) = opcode {
+                        set_pointer(state, pointer.take());
+                    }
+                    execute(opcode, state).is_some()
+                }
+            };
+            if !applies {
+                set_pointer(state, pointer);
+                return Err(steps);
+            }
+            match operation.end {
+                Some(end) => pointer = Some(end),
+                None => {
+                    // branch_2 has turned the pointer but not moved it
+                    let (x, y) = state.location;
+                    let next = self.cells[y * self.cols + x].next[index(state.direction)];
+                    state.location = self.location(next);
+                }
+            }
+            steps += operation.steps;
+        }
+        set_pointer(state, pointer);
+        Ok(steps)
+    }
+
+    /// The index in `traces` of the trace starting at `pointer`.
+    fn start(&self, pointer: Pointer) -> Option<u32> {
+        let (x, y) = pointer.location;
+        self.starts
+            .get(slot(y * self.cols + x, pointer))
+            .cloned()
+            .unwrap_or(None)
+    }
+
+    /// Apply the rule for the instruction at index `i` if it is one of the common ones and applies,
+    /// otherwise leave `state` unchanged and return `None`.
+    fn apply<I: Io>(&self, i: usize, state: &mut State<I, C>) -> Option<Rule> {
+        let cell = &self.cells[i];
+        if !state.multi_digit_accumulator.is_empty() {
+            return None;
+        }
+
+        if state.string_mode {
+            let data = &mut state.data_stack;
+            let rule = if cell.opcodes[0] == Opcode::StringToggle {
+                state.string_mode = false;
+                Rule::StringToggle
+            } else if !state.reverse_mode {
+                data.push(C::from_char(cell.character));
+                Rule::StringPush
+            } else if data.last() == Some(C::from_char(cell.character)) {
+                data.pop();
+                Rule::StringPop
+            } else {
+                return None;
+            };
+            state.location = self.location(cell.next[index(state.direction)]);
+            return Some(rule);
+        }
+
+        let rule = execute(cell.opcodes[state.reverse_mode as usize], state)?;
+        state.location = self.location(cell.next[index(state.direction)]);
+        Some(rule)
+    }
+
+    fn location(&self, i: usize) -> (usize, usize) {
+        self.locations[i]
+    }
+}
+
+fn set_pointer<I: Io, C: Cell>(state: &mut State<I, C>, pointer: Option<Pointer>) {
+    if let Some(pointer) = pointer {
+        state.location = pointer.location;
+        state.direction = pointer.direction;
+        state.reverse_mode = pointer.reverse_mode;
+        state.string_mode = pointer.string_mode;
+    }
+}
+
+/// Apply the rule for `opcode` outside string mode with an empty accumulator if it applies, turning
+/// but not moving the instruction pointer, otherwise leave `state` unchanged and return `None`.
+fn execute<I: Io, C: Cell>(opcode: Opcode, state: &mut State<I, C>) -> Option<Rule> {
+    let data = &mut state.data_stack;
+    let control = &mut state.control_stack;
+    let mut direction = state.direction;
+    let rule = match opcode {
+        Opcode::Nop => Rule::Nop,
+        Opcode::Push => {
+            data.push(C::zero());
+            Rule::Push
+        }
+        Opcode::Pop if data.last().is_some_and(|x| x.is_zero()) => {
+            data.pop();
+            Rule::Pop
+        }
+        Opcode::Transfer1 if !data.is_empty() => {
+            control.push(data.pop().expect("non empty"));
+            Rule::Transfer1
+        }
+        Opcode::Transfer2 if !control.is_empty() => {
+            data.push(control.pop().expect("non empty"));
+            Rule::Transfer2
+        }
+        Opcode::Interchange if !data.is_empty() && !control.is_empty() => {
+            let x = data.pop().expect("non empty");
+            let y = control.pop().expect("non empty");
+            data.push(y);
+            control.push(x);
+            Rule::Interchange
+        }
+        Opcode::Increment if !data.is_empty() => {
+            let x = data.pop().expect("non empty");
+            data.push(x.increment());
+            Rule::Increment
+        }
+        Opcode::Decrement if !data.is_empty() => {
+            let x = data.pop().expect("non empty");
+            data.push(x.decrement());
+            Rule::Decrement
+        }
+        Opcode::Add if data.len() >= 2 => {
+            let x = data.pop().expect("len >= 2");
+            let y = data.pop().expect("len >= 2");
+            data.push(y.add(&x));
+            data.push(x);
+            Rule::Add
+        }
+        Opcode::Subtract if data.len() >= 2 => {
+            let x = data.pop().expect("len >= 2");
+            let y = data.pop().expect("len >= 2");
+            data.push(y.subtract(&x));
+            data.push(x);
+            Rule::Subtract
+        }
+        Opcode::Divide if data.len() >= 2 && !data.last().expect("len >= 2").is_zero() => {
+            let x = data.pop().expect("len >= 2");
+            let y = data.pop().expect("len >= 2");
+            let (quotient, remainder) = y.div_rem(&x);
+            data.push(quotient);
+            data.push(remainder);
+            data.push(x);
+            Rule::Divide
+        }
+        Opcode::Multiply if data.len() >= 3 => {
+            let x = data.pop().expect("len >= 3");
+            let y = data.pop().expect("len >= 3");
+            let z = data.pop().expect("len >= 3");
+            data.push(z.multiply_add(&x, &y));
+            data.push(x);
+            Rule::Multiply
+        }
+        Opcode::Not if !data.is_empty() => {
+            let x = data.pop().expect("non empty");
+            data.push(x.not());
+            Rule::Not
+        }
+        Opcode::And if data.len() >= 3 => {
+            let x = data.pop().expect("len >= 3");
+            let y = data.pop().expect("len >= 3");
+            let z = data.pop().expect("len >= 3");
+            data.push(x.and(&y).xor(&z));
+            data.push(y);
+            data.push(x);
+            Rule::And
+        }
+        Opcode::Or if data.len() >= 3 => {
+            let x = data.pop().expect("len >= 3");
+            let y = data.pop().expect("len >= 3");
+            let z = data.pop().expect("len >= 3");
+            data.push(x.or(&y).xor(&z));
+            data.push(y);
+            data.push(x);
+            Rule::Or
+        }
+        Opcode::Xor if data.len() >= 2 => {
+            let x = data.pop().expect("len >= 2");
+            let y = data.pop().expect("len >= 2");
+            data.push(x.xor(&y));
+            data.push(x);
+            Rule::Xor
+        }
+        Opcode::Toggle if !control.is_empty() => {
+            let c = control.pop().expect("non empty");
+            control.push(c.xor(&C::one()));
+            Rule::Toggle
+        }
+        Opcode::Equal | Opcode::Less | Opcode::Greater
+            if data.len() >= 2 && !control.is_empty() =>
+        {
+            let x = data.get(data.len() - 1).expect("len >= 2");
+            let y = data.get(data.len() - 2).expect("len >= 2");
+            let (holds, rule_true, rule_false) = match opcode {
+                Opcode::Equal => (y == x, Rule::EqualTrue, Rule::EqualFalse),
+                Opcode::Less => (y < x, Rule::LessTrue, Rule::LessFalse),
+                _ => (y > x, Rule::GreaterTrue, Rule::GreaterFalse),
+            };
+            if holds {
+                let c = control.pop().expect("non empty");
+                control.push(c.xor(&C::one()));
+                rule_true
+            } else {
+                rule_false
+            }
+        }
+        Opcode::Swap1 if data.len() >= 2 => {
+            let x = data.pop().expect("len >= 2");
+            let y = data.pop().expect("len >= 2");
+            data.push(x);
+            data.push(y);
+            Rule::Swap1
+        }
+        Opcode::Swap2 if data.len() >= 3 => {
+            let x = data.pop().expect("len >= 3");
+            let y = data.pop().expect("len >= 3");
+            let z = data.pop().expect("len >= 3");
+            data.push(y);
+            data.push(z);
+            data.push(x);
+            Rule::Swap2
+        }
+        Opcode::Swap3 if data.len() >= 3 => {
+            let x = data.pop().expect("len >= 3");
+            let y = data.pop().expect("len >= 3");
+            let z = data.pop().expect("len >= 3");
+            data.push(x);
+            data.push(y);
+            data.push(z);
+            Rule::Swap3
+        }
+        Opcode::Dig if data.len() >= 3 => {
+            let x = data.pop().expect("len >= 3");
+            let y = data.pop().expect("len >= 3");
+            let z = data.pop().expect("len >= 3");
+            data.push(y);
+            data.push(x);
+            data.push(z);
+            Rule::Dig
+        }
+        Opcode::Bury if data.len() >= 3 => {
+            let x = data.pop().expect("len >= 3");
+            let y = data.pop().expect("len >= 3");
+            let z = data.pop().expect("len >= 3");
+            data.push(x);
+            data.push(z);
+            data.push(y);
+            Rule::Bury
+        }
+        Opcode::Over if data.len() >= 2 => {
+            let y = data.get(data.len() - 2).expect("len >= 2");
+            data.push(y);
+            Rule::Over
+        }
+        Opcode::Under if data.len() >= 3 && data.last() == data.get(data.len() - 3) => {
+            data.pop();
+            let x = data.pop().expect("len >= 3");
+            data.push(x);
+            Rule::Under
+        }
+        Opcode::Duplicate if !data.is_empty() => {
+            data.push(data.last().expect("non empty"));
+            Rule::Duplicate
+        }
+        Opcode::Unduplicate if data.len() >= 2 && data.last() == data.get(data.len() - 2) => {
+            data.pop();
+            Rule::Unduplicate
+        }
+        Opcode::StringToggle => {
+            state.string_mode = true;
+            Rule::StringToggle
+        }
+        Opcode::Reverse => {
+            state.reverse_mode = !state.reverse_mode;
+            Rule::Reverse
+        }
+        Opcode::Mirror1 => {
+            direction = direction.mirror();
+            Rule::Mirror1
+        }
+        Opcode::Mirror2 => {
+            direction = direction.mirror().opposite();
+            Rule::Mirror2
+        }
+        Opcode::Branch(d) if d == direction.left() || d == direction.right() => {
+            // push 1 on a right turn, 0 on a left turn, the other way around in reverse mode
+            let right = d == direction.right();
+            control.push(if right != state.reverse_mode {
+                C::one()
+            } else {
+                C::zero()
+            });
+            direction = d;
+            Rule::Branch1
+        }
+        Opcode::Branch(d) if d == direction.opposite() && !control.is_empty() => {
+            let c = control.pop().expect("non empty");
+            direction = if c.is_zero() ^ state.reverse_mode {
+                direction.left()
+            } else {
+                direction.right()
+            };
+            Rule::Branch2
+        }
+        Opcode::Branch(d) if d == direction && !control.is_empty() => {
+            let c = control.pop().expect("non empty");
+            control.push(c.xor(&C::one()));
+            state.reverse_mode = !state.reverse_mode;
+            direction = direction.opposite();
+            Rule::Branch3
+        }
+        _ => return None,
+    };
+    state.direction = direction;
+    Some(rule)
+}
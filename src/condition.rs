@@ -0,0 +1,383 @@
+//! A small expression language over `State`, used for conditional breakpoints.
+//!
+//! ```text
+//! expression := and ("||" and)*
+//! and        := not ("&&" not)*
+//! not        := "!" not | comparison
+//! comparison := term (("==" | "!=" | "<" | "<=" | ">" | ">=") term)?
+//! term       := "(" expression ")" | number | "true" | "false"
+//!             | "north" | "east" | "south" | "west"
+//!             | "data" "[" index "]" | "control" "[" index "]" | "data.len" | "control.len"
+//!             | "line" | "col" | "direction" | "reverse" | "string"
+//! ```
+//!
+//! `data[0]` is the top of the data stack, `data[1]` the entry below it and so on. `line` and `col`
+//! are 1-based, `direction` is the direction the instruction pointer moves in. Every comparison
+//! with an entry beyond the bottom of a stack is false.
+
+use std::fmt;
+
+use cell::Cell;
+use direction::Direction;
+use io::Io;
+use state::State;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Type {
+    Number,
+    Bool,
+    Direction,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expression {
+    /// A literal, converted to a cell when evaluated so it fits any cell type.
+    Number(String),
+    Bool(bool),
+    Direction(Direction),
+    Data(usize),
+    Control(usize),
+    DataLength,
+    ControlLength,
+    Line,
+    Column,
+    Heading,
+    Reverse,
+    StringMode,
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Compare(Comparison, Box<Expression>, Box<Expression>),
+}
+
+enum Value<C: Cell> {
+    Number(C),
+    Bool(bool),
+    Direction(Direction),
+    /// An entry beyond the bottom of a stack.
+    Missing,
+}
+
+impl Expression {
+    fn kind(&self) -> Type {
+        match self {
+            Expression::Number(_)
+            | Expression::Data(_)
+            | Expression::Control(_)
+            | Expression::DataLength
+            | Expression::ControlLength
+            | Expression::Line
+            | Expression::Column => Type::Number,
+            Expression::Direction(_) | Expression::Heading => Type::Direction,
+            Expression::Bool(_)
+            | Expression::Reverse
+            | Expression::StringMode
+            | Expression::Not(_)
+            | Expression::And(..)
+            | Expression::Or(..)
+            | Expression::Compare(..) => Type::Bool,
+        }
+    }
+
+    fn evaluate<I: Io, C: Cell>(&self, state: &State<I, C>) -> Value<C> {
+        let number = |n: usize| Value::Number(C::from_digits(&n.to_string()));
+        let top = |stack: &::stack::Stack<C>, index: usize| match stack
+            .len()
+            .checked_sub(index + 1)
+            .and_then(|i| stack.get(i))
+        {
+            Some(value) => Value::Number(value),
+            None => Value::Missing,
+        };
+
+        match self {
+            Expression::Number(digits) => match digits.strip_prefix('-') {
+                Some(digits) => Value::Number(C::zero().subtract(&C::from_digits(digits))),
+                None => Value::Number(C::from_digits(digits)),
+            },
+            Expression::Bool(b) => Value::Bool(*b),
+            Expression::Direction(d) => Value::Direction(*d),
+            Expression::Data(index) => top(&state.data_stack, *index),
+            Expression::Control(index) => top(&state.control_stack, *index),
+            Expression::DataLength => number(state.data_stack.len()),
+            Expression::ControlLength => number(state.control_stack.len()),
+            Expression::Line => number(state.location.1 + 1),
+            Expression::Column => number(state.location.0 + 1),
+            Expression::Heading => Value::Direction(state.direction),
+            Expression::Reverse => Value::Bool(state.reverse_mode),
+            Expression::StringMode => Value::Bool(state.string_mode),
+            Expression::Not(e) => Value::Bool(!e.holds(state)),
+            Expression::And(a, b) => Value::Bool(a.holds(state) && b.holds(state)),
+            Expression::Or(a, b) => Value::Bool(a.holds(state) || b.holds(state)),
+            Expression::Compare(comparison, a, b) => {
+                Value::Bool(compare(*comparison, a.evaluate(state), b.evaluate(state)))
+            }
+        }
+    }
+
+    fn holds<I: Io, C: Cell>(&self, state: &State<I, C>) -> bool {
+        match self.evaluate(state) {
+            Value::Bool(b) => b,
+            _ => false,
+        }
+    }
+}
+
+fn compare<C: Cell>(comparison: Comparison, a: Value<C>, b: Value<C>) -> bool {
+    use std::cmp::Ordering;
+
+    let ordering = match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.cmp(&b),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(&b),
+        // the type check only allows == and != on directions
+        (Value::Direction(a), Value::Direction(b)) => {
+            return (a == b) == (comparison == Comparison::Equal)
+        }
+        _ => return false,
+    };
+    match comparison {
+        Comparison::Equal => ordering == Ordering::Equal,
+        Comparison::NotEqual => ordering != Ordering::Equal,
+        Comparison::Less => ordering == Ordering::Less,
+        Comparison::LessEqual => ordering != Ordering::Greater,
+        Comparison::Greater => ordering == Ordering::Greater,
+        Comparison::GreaterEqual => ordering != Ordering::Less,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(String),
+    Word(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(s) | Token::Word(s) => write!(f, "{}", s),
+            Token::Symbol(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+const SYMBOLS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "(", ")", "[", "]",
+];
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let c = rest.chars().next().expect("not empty");
+        let length = if c.is_ascii_digit() || c == '-' {
+            let length = 1 + rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len() - 1);
+            if &rest[..length] == "-" {
+                return Err("expected digits after `-`".to_owned());
+            }
+            tokens.push(Token::Number(rest[..length].to_owned()));
+            length
+        } else if c.is_ascii_alphabetic() {
+            let length = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Word(rest[..length].to_owned()));
+            length
+        } else {
+            match SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+                Some(symbol) => {
+                    tokens.push(Token::Symbol(symbol));
+                    symbol.len()
+                }
+                None => return Err(format!("unexpected `{}` in condition", c)),
+            }
+        };
+        rest = rest[length..].trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| "unexpected end of condition".to_owned())?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, symbol: &'static str) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        match self.next()? {
+            Token::Symbol(s) if s == symbol => Ok(()),
+            token => Err(format!("expected `{}`, found `{}`", symbol, token)),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expression, String> {
+        let mut e = self.and()?;
+        while self.eat("||") {
+            e = Expression::Or(Box::new(boolean(e)?), Box::new(boolean(self.and()?)?));
+        }
+        Ok(e)
+    }
+
+    fn and(&mut self) -> Result<Expression, String> {
+        let mut e = self.not()?;
+        while self.eat("&&") {
+            e = Expression::And(Box::new(boolean(e)?), Box::new(boolean(self.not()?)?));
+        }
+        Ok(e)
+    }
+
+    fn not(&mut self) -> Result<Expression, String> {
+        if self.eat("!") {
+            Ok(Expression::Not(Box::new(boolean(self.not()?)?)))
+        } else {
+            self.comparison()
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expression, String> {
+        let a = self.term()?;
+        let comparison = match self.peek() {
+            Some(Token::Symbol("==")) => Comparison::Equal,
+            Some(Token::Symbol("!=")) => Comparison::NotEqual,
+            Some(Token::Symbol("<")) => Comparison::Less,
+            Some(Token::Symbol("<=")) => Comparison::LessEqual,
+            Some(Token::Symbol(">")) => Comparison::Greater,
+            Some(Token::Symbol(">=")) => Comparison::GreaterEqual,
+            _ => return Ok(a),
+        };
+        self.position += 1;
+        let b = self.term()?;
+
+        if a.kind() != b.kind() {
+            return Err("can not compare values of different types".to_owned());
+        }
+        let ordered = comparison == Comparison::Equal || comparison == Comparison::NotEqual;
+        if a.kind() != Type::Number && !ordered {
+            return Err("only numbers can be ordered".to_owned());
+        }
+        Ok(Expression::Compare(comparison, Box::new(a), Box::new(b)))
+    }
+
+    fn term(&mut self) -> Result<Expression, String> {
+        let word = match self.next()? {
+            Token::Number(digits) => return Ok(Expression::Number(digits)),
+            Token::Symbol("(") => {
+                let e = self.expression()?;
+                self.expect(")")?;
+                return Ok(e);
+            }
+            Token::Symbol(s) => return Err(format!("unexpected `{}` in condition", s)),
+            Token::Word(word) => word,
+        };
+
+        let e = match word.as_str() {
+            "true" => Expression::Bool(true),
+            "false" => Expression::Bool(false),
+            "north" => Expression::Direction(Direction::North),
+            "east" => Expression::Direction(Direction::East),
+            "south" => Expression::Direction(Direction::South),
+            "west" => Expression::Direction(Direction::West),
+            "data.len" => Expression::DataLength,
+            "control.len" => Expression::ControlLength,
+            "line" => Expression::Line,
+            "col" => Expression::Column,
+            "direction" => Expression::Heading,
+            "reverse" => Expression::Reverse,
+            "string" => Expression::StringMode,
+            "data" | "control" => {
+                self.expect("[")?;
+                let index = match self.next()? {
+                    Token::Number(ref n) if !n.starts_with('-') => n
+                        .parse()
+                        .map_err(|_| format!("index `{}` is too large", n))?,
+                    token => return Err(format!("expected an index, found `{}`", token)),
+                };
+                self.expect("]")?;
+                if word == "data" {
+                    Expression::Data(index)
+                } else {
+                    Expression::Control(index)
+                }
+            }
+            _ => return Err(format!("unknown name `{}` in condition", word)),
+        };
+        Ok(e)
+    }
+}
+
+fn boolean(e: Expression) -> Result<Expression, String> {
+    if e.kind() == Type::Bool {
+        Ok(e)
+    } else {
+        Err("expected a condition, found a value".to_owned())
+    }
+}
+
+/// A parsed condition, which holds or does not hold in a state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    source: String,
+    expression: Expression,
+}
+
+impl Condition {
+    pub fn parse(s: &str) -> Result<Condition, String> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+        let expression = boolean(parser.expression()?)?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected `{}` in condition", token));
+        }
+        Ok(Condition {
+            source: s.trim().to_owned(),
+            expression,
+        })
+    }
+
+    pub fn holds<I: Io, C: Cell>(&self, state: &State<I, C>) -> bool {
+        self.expression.holds(state)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
@@ -0,0 +1,478 @@
+//! An interactive debugger that steps through a program and stops at breakpoints.
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use cell::Cell;
+use condition::Condition;
+use instruction::{ExecutionError, InstructionExecutionStatus};
+use io::Io;
+use rule::Rule;
+use state::{State, StepBackError};
+
+/// Where `Debugger::continue_execution` and `Debugger::reverse_continue` stop.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Breakpoint {
+    /// Stop before executing the instruction at this (x, y) location.
+    Location((usize, usize)),
+    /// Stop before executing this instruction, wherever it is.
+    Instruction(char),
+    /// Stop when the condition becomes true, that is when it holds after a step but did not hold
+    /// before it.
+    Condition(Condition),
+    /// Stop when any of this many entries at the top of the data stack change.
+    Watch(usize),
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Breakpoint::Location((x, y)) => write!(f, "at line {}, col {}", y + 1, x + 1),
+            Breakpoint::Instruction(c) => write!(f, "at instruction `{}`", c),
+            Breakpoint::Condition(condition) => write!(f, "when `{}` becomes true", condition),
+            Breakpoint::Watch(1) => write!(f, "when the top of the data stack changes"),
+            Breakpoint::Watch(n) => {
+                write!(f, "when the top {} entries of the data stack change", n)
+            }
+        }
+    }
+}
+
+/// What a breakpoint saw after the last step, to tell whether the next step changed it.
+#[derive(Clone, Debug, PartialEq)]
+enum Seen<C: Cell> {
+    Nothing,
+    Condition(bool),
+    Top(Vec<C>),
+}
+
+/// Why the debugger stopped executing.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stop<C: Cell = i32> {
+    /// The requested number of steps was taken.
+    Done,
+    /// The breakpoint with this index was hit.
+    Breakpoint(usize),
+    Halted,
+    Stuck(ExecutionError<C>),
+    /// Execution can not go further backwards.
+    StepBack(StepBackError<C>),
+}
+
+/// A command of the debugger REPL.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Step(usize),
+    Continue,
+    Back(usize),
+    ReverseContinue,
+    Break(Breakpoint),
+    Delete(usize),
+    Breakpoints,
+    Print,
+    Grid,
+    Help,
+    Quit,
+}
+
+const HELP: &str = "\
+step [N]          execute N instructions (default 1)
+continue          execute until a breakpoint is hit or the program halts
+back [N]          undo N instructions (default 1), also called reverse-step
+reverse-continue  undo instructions until a breakpoint is hit or the start is reached
+break LINE COL    stop before executing the instruction at LINE, COL
+break OPCODE      stop before executing OPCODE anywhere in the program
+break if COND     stop when the condition COND becomes true, e.g. `data.len > 3`,
+                  `data[0] == 10`, `control.len == 0`, `reverse` or
+                  `line == 2 && col == 5 && direction == west`
+watch [N]         stop when any of the top N entries of the data stack change (default 1)
+delete N          remove breakpoint N
+breakpoints       list the breakpoints
+print             show the stacks, direction and modes
+grid              show the program with the instruction pointer highlighted
+help              show this message
+quit              leave the debugger
+Short forms: `s` step, `c` continue, `rs` back, `rc` reverse-continue, `b` break, `w` watch,
+`d` delete, `p` print, `g` grid, `h` help and `q` quit. An empty line repeats the last command.";
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        // a condition is an expression that may contain spaces, so it is parsed as a whole
+        let mut parts = line.trim().splitn(2, char::is_whitespace);
+        if let (Some("break"), Some(rest)) | (Some("b"), Some(rest)) = (parts.next(), parts.next())
+        {
+            let rest = rest.trim_start();
+            if rest.starts_with("if ") || rest.starts_with("if(") {
+                let condition = Condition::parse(&rest[2..])?;
+                return Ok(Command::Break(Breakpoint::Condition(condition)));
+            }
+        }
+
+        let mut words = line.split_whitespace();
+        let command = words.next().ok_or_else(|| "empty command".to_owned())?;
+        let args: Vec<&str> = words.collect();
+        let number = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|_| format!("expected a number, found `{}`", s))
+        };
+
+        let command = match (command, args.as_slice()) {
+            ("step", []) | ("s", []) => Command::Step(1),
+            ("step", [n]) | ("s", [n]) => Command::Step(number(n)?),
+            ("continue", []) | ("c", []) => Command::Continue,
+            ("back", []) | ("reverse-step", []) | ("rs", []) => Command::Back(1),
+            ("back", [n]) | ("reverse-step", [n]) | ("rs", [n]) => Command::Back(number(n)?),
+            ("reverse-continue", []) | ("rc", []) => Command::ReverseContinue,
+            ("break", [line, col]) | ("b", [line, col]) => {
+                let (line, col) = (number(line)?, number(col)?);
+                if line == 0 || col == 0 {
+                    return Err("lines and columns start at 1".to_owned());
+                }
+                Command::Break(Breakpoint::Location((col - 1, line - 1)))
+            }
+            ("break", [opcode]) | ("b", [opcode]) if opcode.chars().count() == 1 => Command::Break(
+                Breakpoint::Instruction(opcode.chars().next().expect("one char")),
+            ),
+            ("watch", []) | ("w", []) => Command::Break(Breakpoint::Watch(1)),
+            ("watch", [n]) | ("w", [n]) => match number(n)? {
+                0 => return Err("a watch needs at least one entry".to_owned()),
+                n => Command::Break(Breakpoint::Watch(n)),
+            },
+            ("delete", [n]) | ("d", [n]) => Command::Delete(number(n)?),
+            ("breakpoints", []) => Command::Breakpoints,
+            ("print", []) | ("p", []) => Command::Print,
+            ("grid", []) | ("g", []) => Command::Grid,
+            ("help", []) | ("h", []) => Command::Help,
+            ("quit", []) | ("q", []) => Command::Quit,
+            _ => return Err(format!("unknown command `{}`, try `help`", line.trim())),
+        };
+
+        Ok(command)
+    }
+}
+
+pub struct Debugger<I: Io, C: Cell = i32> {
+    pub state: State<I, C>,
+    breakpoints: Vec<Breakpoint>,
+    seen: Vec<Seen<C>>,
+    /// The rule applied by the last step, forwards or backwards.
+    last_rule: Option<Rule>,
+    steps: usize,
+    halted: bool,
+}
+
+impl<I: Io, C: Cell> Debugger<I, C> {
+    pub fn new(state: State<I, C>) -> Self {
+        Self {
+            state,
+            breakpoints: Vec::new(),
+            seen: Vec::new(),
+            last_rule: None,
+            steps: 0,
+            halted: false,
+        }
+    }
+
+    /// The number of steps executed so far.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// The rule applied by the last step, forwards or backwards.
+    pub fn last_rule(&self) -> Option<Rule> {
+        self.last_rule
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.seen.push(self.look(&breakpoint));
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    pub fn remove_breakpoint(&mut self, index: usize) -> Option<Breakpoint> {
+        if index < self.breakpoints.len() {
+            self.seen.remove(index);
+            Some(self.breakpoints.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// What `breakpoint` sees in the current state.
+    fn look(&self, breakpoint: &Breakpoint) -> Seen<C> {
+        match breakpoint {
+            Breakpoint::Location(_) | Breakpoint::Instruction(_) => Seen::Nothing,
+            Breakpoint::Condition(condition) => Seen::Condition(condition.holds(&self.state)),
+            Breakpoint::Watch(n) => {
+                let stack = &self.state.data_stack;
+                let bottom = stack.len().saturating_sub(*n);
+                Seen::Top((bottom..stack.len()).filter_map(|i| stack.get(i)).collect())
+            }
+        }
+    }
+
+    /// Update what every breakpoint sees after a step, returning the index of the first one that
+    /// is hit.
+    fn check(&mut self) -> Option<usize> {
+        let location = self.state.location;
+        let instruction = self
+            .state
+            .program
+            .instruction_at(location)
+            .expect("location in state should always give an instruction.")
+            .to_string();
+
+        let mut hit = None;
+        for i in 0..self.breakpoints.len() {
+            let seen = self.look(&self.breakpoints[i]);
+            let stop = match (&self.breakpoints[i], &seen) {
+                (Breakpoint::Location(l), _) => *l == location,
+                (Breakpoint::Instruction(c), _) => instruction.starts_with(*c),
+                (Breakpoint::Condition(_), Seen::Condition(holds)) => {
+                    *holds && self.seen[i] == Seen::Condition(false)
+                }
+                (_, seen) => *seen != self.seen[i],
+            };
+            self.seen[i] = seen;
+            if stop && hit.is_none() {
+                hit = Some(i);
+            }
+        }
+        hit
+    }
+
+    /// Execute a single instruction.
+    fn execute(&mut self) -> Option<Stop<C>> {
+        if self.halted {
+            return Some(Stop::Halted);
+        }
+        match self.state.step() {
+            Ok(InstructionExecutionStatus::Successful(rule)) => {
+                self.last_rule = Some(rule);
+                self.steps += 1;
+                None
+            }
+            Ok(InstructionExecutionStatus::Halt) => {
+                self.halted = true;
+                Some(Stop::Halted)
+            }
+            Err(e) => Some(Stop::Stuck(e)),
+        }
+    }
+
+    /// Undo a single instruction.
+    fn execute_back(&mut self) -> Option<Stop<C>> {
+        // nothing has been executed yet, whatever the cell behind the pointer is
+        if self.steps == 0 {
+            return Some(Stop::StepBack(StepBackError::AtStart));
+        }
+        // a halt does not move, so the state before it is the state after it
+        match self.state.step_back() {
+            Ok(rule) => {
+                self.last_rule = Some(rule);
+                self.halted = false;
+                self.steps -= 1;
+                None
+            }
+            Err(e) => Some(Stop::StepBack(e)),
+        }
+    }
+
+    /// Execute `n` instructions, stopping at a breakpoint hit by any of them, the last one too.
+    ///
+    /// Breakpoints are checked after every step, so stepping away from a breakpoint is always
+    /// possible.
+    pub fn step(&mut self, n: usize) -> Stop<C> {
+        for _ in 0..n {
+            if let Some(stop) = self.execute() {
+                return stop;
+            }
+            if let Some(b) = self.check() {
+                return Stop::Breakpoint(b);
+            }
+        }
+        Stop::Done
+    }
+
+    /// Execute until a breakpoint is hit, the program halts or it gets stuck.
+    pub fn continue_execution(&mut self) -> Stop<C> {
+        loop {
+            if let Some(stop) = self.execute() {
+                return stop;
+            }
+            if let Some(b) = self.check() {
+                return Stop::Breakpoint(b);
+            }
+        }
+    }
+
+    /// Undo `n` instructions, stopping at a breakpoint hit by any of them, the last one too.
+    ///
+    /// Nothing is recorded while running forwards: every step is undone by executing the inverse of
+    /// the instruction behind the instruction pointer, see `State::step_back`.
+    pub fn back(&mut self, n: usize) -> Stop<C> {
+        for _ in 0..n {
+            if let Some(stop) = self.execute_back() {
+                return stop;
+            }
+            if let Some(b) = self.check() {
+                return Stop::Breakpoint(b);
+            }
+        }
+        Stop::Done
+    }
+
+    /// Undo instructions until a breakpoint is hit or execution can not go further back.
+    pub fn reverse_continue(&mut self) -> Stop<C> {
+        loop {
+            if let Some(stop) = self.execute_back() {
+                return stop;
+            }
+            if let Some(b) = self.check() {
+                return Stop::Breakpoint(b);
+            }
+        }
+    }
+
+    /// The rule applied last, the stacks, direction, modes and the next instruction.
+    pub fn describe_state(&self) -> String {
+        let state = &self.state;
+        let rule = match self.last_rule {
+            Some(rule) => format!("rule:    {} ({})\n", rule, rule.description()),
+            None => String::new(),
+        };
+        format!(
+            "{}\
+             step {}, line {}, col {}, going {}, reverse mode {}, string mode {}, accumulator '{}'\n\
+             data:    {}\n\
+             control: {}\n\
+             next:    `{}`",
+            rule,
+            self.steps,
+            state.location.1 + 1,
+            state.location.0 + 1,
+            state.direction,
+            if state.reverse_mode { "on" } else { "off" },
+            if state.string_mode { "on" } else { "off" },
+            state.multi_digit_accumulator,
+            state.data_stack,
+            state.control_stack,
+            state.instr()
+        )
+    }
+
+    /// The program grid with the instruction pointer shown in reverse video.
+    pub fn describe_grid(&self) -> String {
+        let mut lines = Vec::new();
+        for (y, line) in self.state.program.to_string().lines().enumerate() {
+            let mut s = String::new();
+            for (x, c) in line.chars().enumerate() {
+                if (x, y) == self.state.location {
+                    s.push_str(&format!("\x1b[7m{}\x1b[0m", c));
+                } else {
+                    s.push(c);
+                }
+            }
+            lines.push(s);
+        }
+        lines.join("\n")
+    }
+
+    fn describe_stop(&self, stop: &Stop<C>) -> String {
+        match stop {
+            Stop::Done => self.describe_state(),
+            Stop::Breakpoint(b) => format!(
+                "breakpoint {} {}\n{}",
+                b,
+                self.breakpoints[*b],
+                self.describe_state()
+            ),
+            Stop::Halted => format!("program halted after {} steps", self.steps),
+            Stop::Stuck(e) => format!("error: {}", e),
+            Stop::StepBack(e) => format!("{}\n{}", e, self.describe_state()),
+        }
+    }
+
+    /// Execute `command`, returning the text to show the user.
+    pub fn execute_command(&mut self, command: &Command) -> String {
+        match *command {
+            Command::Step(n) => {
+                let stop = self.step(n);
+                self.describe_stop(&stop)
+            }
+            Command::Continue => {
+                let stop = self.continue_execution();
+                self.describe_stop(&stop)
+            }
+            Command::Back(n) => {
+                let stop = self.back(n);
+                self.describe_stop(&stop)
+            }
+            Command::ReverseContinue => {
+                let stop = self.reverse_continue();
+                self.describe_stop(&stop)
+            }
+            Command::Break(ref b) => {
+                let index = self.add_breakpoint(b.clone());
+                format!("breakpoint {} {}", index, b)
+            }
+            Command::Delete(index) => match self.remove_breakpoint(index) {
+                Some(b) => format!("deleted breakpoint {} {}", index, b),
+                None => format!("no breakpoint {}", index),
+            },
+            Command::Breakpoints if self.breakpoints.is_empty() => "no breakpoints".to_owned(),
+            Command::Breakpoints => self
+                .breakpoints
+                .iter()
+                .enumerate()
+                .map(|(i, b)| format!("{}: {}", i, b))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Command::Print => self.describe_state(),
+            Command::Grid => self.describe_grid(),
+            Command::Help => HELP.to_owned(),
+            Command::Quit => String::new(),
+        }
+    }
+
+    /// Read commands from `input` until `quit` or the end of the input, writing the responses to
+    /// `output`.
+    pub fn repl<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        writeln!(output, "{}", self.describe_state())?;
+        let mut last = None;
+        let mut lines = input.lines();
+        loop {
+            write!(output, "(befreak) ")?;
+            output.flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+
+            let command = if line.trim().is_empty() {
+                match last.clone() {
+                    Some(command) => command,
+                    None => continue,
+                }
+            } else {
+                match Command::parse(&line) {
+                    Ok(command) => command,
+                    Err(e) => {
+                        writeln!(output, "{}", e)?;
+                        continue;
+                    }
+                }
+            };
+            if command == Command::Quit {
+                return Ok(());
+            }
+
+            writeln!(output, "{}", self.execute_command(&command))?;
+            last = Some(command);
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Direction {
     North,
     East,
@@ -48,7 +48,8 @@ impl Direction {
             Direction::East => "\\rightarrow",
             Direction::South => "\\downarrow",
             Direction::West => "\\leftarrow",
-        }.to_string()
+        }
+        .to_string()
     }
 }
 
@@ -0,0 +1,124 @@
+//! Renders the program grid with every cell colored by how often it was executed, to see which
+//! parts of a program are hot and which are never reached.
+//!
+//! Counts are shown on a logarithmic scale from blue (executed once) to red (executed the most).
+//! Instructions that were never executed are marked in grey, spaces that were never executed are
+//! left blank.
+
+use statistics::Statistics;
+
+/// The 256-color palette entries used for the ANSI heatmap, from cold to hot.
+const ANSI_COLORS: &[u8] = &[
+    21, 27, 33, 39, 45, 51, 50, 49, 48, 47, 46, 82, 118, 154, 190, 226, 220, 214, 208, 202, 196,
+];
+/// The 256-color palette entry for instructions that were never executed.
+const ANSI_UNREACHED: u8 = 240;
+
+/// The size of a cell in the SVG heatmap, in pixels.
+const SVG_WIDTH: usize = 12;
+const SVG_HEIGHT: usize = 20;
+
+/// How hot a cell executed `hits` times is compared to the hottest cell, from 0 to 1.
+fn heat(hits: usize, max: usize) -> f64 {
+    if max <= 1 {
+        1.0
+    } else {
+        ((hits as f64).ln() / (max as f64).ln()).clamp(0.0, 1.0)
+    }
+}
+
+fn max_hits(statistics: &Statistics) -> usize {
+    statistics
+        .cells
+        .iter()
+        .flat_map(|row| row.iter().cloned())
+        .max()
+        .unwrap_or(0)
+}
+
+/// The heatmap with ANSI escape codes, for terminals that support 256 colors.
+pub fn ansi(statistics: &Statistics) -> String {
+    let max = max_hits(statistics);
+    let mut lines = Vec::new();
+    for (y, row) in statistics.cells.iter().enumerate() {
+        let mut s = String::new();
+        for (x, &hits) in row.iter().enumerate() {
+            let c = statistics.instruction_at((x, y));
+            if hits > 0 {
+                let i = (heat(hits, max) * (ANSI_COLORS.len() - 1) as f64).round() as usize;
+                s.push_str(&format!("\x1b[30;48;5;{}m{}\x1b[0m", ANSI_COLORS[i], c));
+            } else if c != ' ' {
+                s.push_str(&format!("\x1b[38;5;{}m{}\x1b[0m", ANSI_UNREACHED, c));
+            } else {
+                s.push(c);
+            }
+        }
+        lines.push(s);
+    }
+    lines.push(format!(
+        "executed 1 to {} times from blue to red, never executed in grey",
+        max
+    ));
+    lines.join("\n")
+}
+
+/// The heatmap as a standalone SVG image, every executed cell has a tooltip with its count.
+pub fn svg(statistics: &Statistics) -> String {
+    let max = max_hits(statistics);
+    let rows = statistics.cells.len();
+    let cols = statistics.cells.first().map_or(0, |row| row.len());
+    let mut s = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         font-family=\"monospace\" font-size=\"16\" text-anchor=\"middle\">\n",
+        cols * SVG_WIDTH,
+        rows * SVG_HEIGHT
+    );
+    s.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+    for (y, row) in statistics.cells.iter().enumerate() {
+        for (x, &hits) in row.iter().enumerate() {
+            let c = statistics.instruction_at((x, y));
+            let (fill, text, title) = if hits > 0 {
+                // hue 240 is blue, 0 is red
+                let hue = (240.0 * (1.0 - heat(hits, max))).round();
+                let title = if hits == 1 {
+                    "executed once".to_owned()
+                } else {
+                    format!("executed {} times", hits)
+                };
+                (format!("hsl({}, 90%, 60%)", hue), "black", title)
+            } else if c != ' ' {
+                ("#e0e0e0".to_owned(), "#808080", "never executed".to_owned())
+            } else {
+                continue;
+            };
+            s.push_str(&format!(
+                "<g><title>line {}, col {}: {}</title>\
+                 <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\
+                 <text x=\"{}\" y=\"{}\" fill=\"{}\">{}</text></g>\n",
+                y + 1,
+                x + 1,
+                title,
+                x * SVG_WIDTH,
+                y * SVG_HEIGHT,
+                SVG_WIDTH,
+                SVG_HEIGHT,
+                fill,
+                x * SVG_WIDTH + SVG_WIDTH / 2,
+                y * SVG_HEIGHT + SVG_HEIGHT * 3 / 4,
+                text,
+                escape(c)
+            ));
+        }
+    }
+    s.push_str("</svg>\n");
+    s
+}
+
+fn escape(c: char) -> String {
+    match c {
+        '&' => "&amp;".to_owned(),
+        '<' => "&lt;".to_owned(),
+        '>' => "&gt;".to_owned(),
+        c => c.to_string(),
+    }
+}
@@ -1,11 +1,17 @@
-use std::char;
+use std::error::Error;
 use std::fmt;
-use std::io::{self, Read};
+use std::io;
 
+use cell::Cell;
+use compiled::Opcode;
 use direction::Direction;
+use io::Io;
+use pointer::{Flow, Pointer};
+use program::Program;
+use rule::Rule;
 use state::State;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Instruction {
     c: char,
 }
@@ -25,7 +31,7 @@ const SUBTRACT: char = '-';
 const DIVIDE: char = '%';
 const MULTIPLY: char = '*';
 const NOT: char = '~';
-const AND: char = '+';
+const AND: char = '&';
 const OR: char = '|';
 const XOR: char = '#';
 const ROTATE_LEFT: char = '{';
@@ -53,12 +59,170 @@ const BRANCH_WEST: char = '<';
 const BRANCH_SOUTH: char = 'v';
 const BRANCH_NORTH: char = '^';
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum InstructionExecutionStatus {
-    Successful(&'static str),
-    Unsuccessful,
+    Successful(Rule),
     Halt,
 }
 
+/// The stacks of a `State`, used to tell which one an `ExecutionError` is about.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StackKind {
+    Data,
+    Control,
+    Input,
+    Output,
+}
+
+impl fmt::Display for StackKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                StackKind::Data => "data stack",
+                StackKind::Control => "control stack",
+                StackKind::Input => "input stack",
+                StackKind::Output => "output stack",
+            }
+        )
+    }
+}
+
+/// The reason no rule applied to an instruction.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExecutionErrorKind<C: Cell = i32> {
+    /// The stack holds fewer elements than the instruction needs.
+    StackUnderflow {
+        stack: StackKind,
+        required: usize,
+        found: usize,
+    },
+    /// `)` can only pop a zero.
+    PopNonZero {
+        top: C,
+    },
+    /// `;` can only remove the top of the data stack if it equals the element below it.
+    UnduplicateMismatch {
+        top: C,
+        second: C,
+    },
+    /// `u` can only remove the second element if the top equals the third element.
+    UnderMismatch {
+        top: C,
+        third: C,
+    },
+    DivisionByZero,
+    /// `w` can only write values that are ASCII characters.
+    NonAsciiWrite {
+        value: C,
+    },
+    /// `r` in reverse mode can only unread values that are printable ASCII characters, the ones
+    /// `r` reads.
+    NonAsciiUnread {
+        value: C,
+    },
+    /// There is no more input to read.
+    InputExhausted,
+    /// The byte read is not a printable ASCII character.
+    InvalidInput {
+        byte: u8,
+    },
+    /// Rotation needs a bit width, which unbounded cells do not have.
+    UnboundedRotation,
+    /// The io backend failed to read or write.
+    Io(io::ErrorKind),
+    /// In reverse string mode the top of the data stack must equal the character being popped.
+    StringMismatch {
+        expected: char,
+        found: C,
+    },
+    /// The instruction is not part of the language.
+    UnknownInstruction,
+    /// The start symbol `@` can not be executed in reverse mode.
+    HaltInReverse,
+}
+
+impl<C: Cell> fmt::Display for ExecutionErrorKind<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ExecutionErrorKind::*;
+        match self {
+            StackUnderflow {
+                stack,
+                required,
+                found,
+            } => write!(
+                f,
+                "stack underflow on the {}, need {} element{} but found {}",
+                stack,
+                required,
+                if *required == 1 { "" } else { "s" },
+                found
+            ),
+            PopNonZero { top } => write!(f, "can not pop non-zero value {}", top),
+            UnduplicateMismatch { top, second } => write!(
+                f,
+                "can not unduplicate, top {} differs from second element {}",
+                top, second
+            ),
+            UnderMismatch { top, third } => write!(
+                f,
+                "can not under, top {} differs from third element {}",
+                top, third
+            ),
+            DivisionByZero => write!(f, "division by zero"),
+            NonAsciiWrite { value } => write!(f, "can not write non-ASCII value {}", value),
+            NonAsciiUnread { value } => write!(
+                f,
+                "can not unread value {}, which is not printable ASCII",
+                value
+            ),
+            InputExhausted => write!(f, "input exhausted"),
+            InvalidInput { byte } => {
+                write!(f, "read byte {:#04x}, which is not printable ASCII", byte)
+            }
+            StringMismatch { expected, found } => write!(
+                f,
+                "can not pop {:?} in string mode, top of the data stack is {}",
+                expected, found
+            ),
+            UnboundedRotation => write!(f, "can not rotate a cell without a bit width"),
+            Io(kind) => write!(f, "i/o error: {:?}", kind),
+            UnknownInstruction => write!(f, "unknown instruction"),
+            HaltInReverse => write!(f, "reached the start symbol in reverse mode"),
+        }
+    }
+}
+
+/// An instruction could not be executed, together with the state it got stuck in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExecutionError<C: Cell = i32> {
+    pub kind: ExecutionErrorKind<C>,
+    pub instruction: Instruction,
+    pub location: (usize, usize),
+    pub direction: Direction,
+    pub reverse_mode: bool,
+    pub string_mode: bool,
+}
+
+impl<C: Cell> fmt::Display for ExecutionError<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, col {} (instruction `{}`, direction {}, reverse mode {}, string mode {})",
+            self.kind,
+            self.location.1 + 1,
+            self.location.0 + 1,
+            self.instruction,
+            self.direction,
+            if self.reverse_mode { "on" } else { "off" },
+            if self.string_mode { "on" } else { "off" },
+        )
+    }
+}
+
+impl<C: Cell> Error for ExecutionError<C> {}
+
 impl Instruction {
     pub fn from_char(c: char) -> Option<Self> {
         if c.is_ascii() && !c.is_ascii_control() {
@@ -76,6 +240,60 @@ impl Instruction {
         self.c == HALT
     }
 
+    pub fn is_digit(self) -> bool {
+        self.c.is_ascii_digit()
+    }
+
+    /// Whether this is an instruction of the language, the others only work in string mode.
+    pub fn is_known(self) -> bool {
+        self.is_digit()
+            || [
+                NOP,
+                PUSH,
+                POP,
+                TRANSFER_TOP_DATA_CONTROL,
+                TRANSFER_TOP_CONTROL_DATA,
+                INTERCHANGE_TOPS,
+                WRITE,
+                READ,
+                INCREMENT,
+                DECREMENT,
+                ADD,
+                SUBTRACT,
+                DIVIDE,
+                MULTIPLY,
+                NOT,
+                AND,
+                OR,
+                XOR,
+                ROTATE_LEFT,
+                ROTATE_RIGHT,
+                CONTROL_TOGGLE,
+                EQUAL,
+                LESS,
+                GREATER,
+                SWAP_TWO_TOP,
+                DIG,
+                BURY,
+                SWAP_FIRST_THIRD,
+                SWAP_SECOND_THIRD,
+                OVER,
+                UNDER,
+                DUPLICATE,
+                UNDUPLICATE,
+                STRING_MODE,
+                REVERSE_MODE,
+                HALT,
+                MIRROR_BACK,
+                MIRROR_FORWARD,
+                BRANCH_EAST,
+                BRANCH_WEST,
+                BRANCH_SOUTH,
+                BRANCH_NORTH,
+            ]
+            .contains(&self.c)
+    }
+
     pub fn inv(self) -> Self {
         let c = match self.c {
             PUSH => POP,
@@ -96,14 +314,68 @@ impl Instruction {
             UNDER => OVER,
             DUPLICATE => UNDUPLICATE,
             UNDUPLICATE => DUPLICATE,
-            HALT => panic!(),
             i => i,
         };
 
         Instruction { c }
     }
 
-    fn direction(self) -> Option<Direction> {
+    /// The opcode of this instruction in a `CompiledProgram`, outside string mode.
+    pub(crate) fn opcode(self) -> Opcode {
+        match self.c {
+            NOP => Opcode::Nop,
+            PUSH => Opcode::Push,
+            POP => Opcode::Pop,
+            TRANSFER_TOP_DATA_CONTROL => Opcode::Transfer1,
+            TRANSFER_TOP_CONTROL_DATA => Opcode::Transfer2,
+            INTERCHANGE_TOPS => Opcode::Interchange,
+            INCREMENT => Opcode::Increment,
+            DECREMENT => Opcode::Decrement,
+            ADD => Opcode::Add,
+            SUBTRACT => Opcode::Subtract,
+            DIVIDE => Opcode::Divide,
+            MULTIPLY => Opcode::Multiply,
+            NOT => Opcode::Not,
+            AND => Opcode::And,
+            OR => Opcode::Or,
+            XOR => Opcode::Xor,
+            CONTROL_TOGGLE => Opcode::Toggle,
+            EQUAL => Opcode::Equal,
+            LESS => Opcode::Less,
+            GREATER => Opcode::Greater,
+            SWAP_TWO_TOP => Opcode::Swap1,
+            SWAP_SECOND_THIRD => Opcode::Swap2,
+            SWAP_FIRST_THIRD => Opcode::Swap3,
+            DIG => Opcode::Dig,
+            BURY => Opcode::Bury,
+            OVER => Opcode::Over,
+            UNDER => Opcode::Under,
+            DUPLICATE => Opcode::Duplicate,
+            UNDUPLICATE => Opcode::Unduplicate,
+            STRING_MODE => Opcode::StringToggle,
+            REVERSE_MODE => Opcode::Reverse,
+            MIRROR_BACK => Opcode::Mirror1,
+            MIRROR_FORWARD => Opcode::Mirror2,
+            BRANCH_EAST | BRANCH_NORTH | BRANCH_SOUTH | BRANCH_WEST => {
+                Opcode::Branch(self.direction().expect("self is a branching instruction"))
+            }
+            READ => Opcode::Read,
+            WRITE => Opcode::Write,
+            ROTATE_LEFT => Opcode::RotateLeft,
+            ROTATE_RIGHT => Opcode::RotateRight,
+            // numbers, halting and unknown instructions
+            _ => Opcode::Interpret,
+        }
+    }
+
+    /// Whether this instruction can change the direction or mode of the instruction pointer outside
+    /// string mode, that is a branch, a mirror or `?`.
+    pub fn is_control_flow(self) -> bool {
+        self.direction().is_some() || [MIRROR_BACK, MIRROR_FORWARD, REVERSE_MODE].contains(&self.c)
+    }
+
+    /// The direction a branch instruction points in, `None` for other instructions.
+    pub fn direction(self) -> Option<Direction> {
         match self.c {
             BRANCH_EAST => Some(Direction::East),
             BRANCH_WEST => Some(Direction::West),
@@ -113,62 +385,204 @@ impl Instruction {
         }
     }
 
-    pub fn execute(self, state: &mut State) -> InstructionExecutionStatus {
+    /// Where this instruction sends `pointer`, see `Pointer::flow`.
+    pub fn flow(self, pointer: Pointer, program: &Program) -> Flow {
+        let mut next = pointer;
+        match self.c {
+            STRING_MODE => next.string_mode = !next.string_mode,
+            // every other character is pushed or popped in string mode
+            _ if pointer.string_mode => {}
+            HALT if pointer.reverse_mode => return Flow::Stuck,
+            HALT => return Flow::Halt,
+            _ if !self.is_known() => return Flow::Stuck,
+            REVERSE_MODE => next.reverse_mode = !next.reverse_mode,
+            MIRROR_BACK => next.direction = pointer.direction.mirror(),
+            MIRROR_FORWARD => next.direction = pointer.direction.mirror().opposite(),
+            BRANCH_EAST | BRANCH_NORTH | BRANCH_SOUTH | BRANCH_WEST => {
+                let d = self.direction().expect("self is a branching instruction");
+                if d == pointer.direction.opposite() {
+                    // branch_2, turn left on a zero (right in reverse mode)
+                    let left = Pointer {
+                        direction: pointer.direction.left(),
+                        ..pointer
+                    };
+                    let right = Pointer {
+                        direction: pointer.direction.right(),
+                        ..pointer
+                    };
+                    let (zero, nonzero) = if pointer.reverse_mode {
+                        (right, left)
+                    } else {
+                        (left, right)
+                    };
+                    return Flow::Branch {
+                        zero: zero.advance(program),
+                        nonzero: nonzero.advance(program),
+                    };
+                } else if d == pointer.direction {
+                    // branch_3
+                    next.direction = d.opposite();
+                    next.reverse_mode = !next.reverse_mode;
+                } else {
+                    // branch_1
+                    next.direction = d;
+                }
+            }
+            _ => {}
+        }
+        Flow::Next(next.advance(program))
+    }
+
+    fn error<I: Io, C: Cell>(
+        self,
+        state: &State<I, C>,
+        kind: ExecutionErrorKind<C>,
+    ) -> ExecutionError<C> {
+        ExecutionError {
+            kind,
+            instruction: self,
+            location: state.location,
+            direction: state.direction,
+            reverse_mode: state.reverse_mode,
+            string_mode: state.string_mode,
+        }
+    }
+
+    /// The number of elements the data and control stack need for a rule of this instruction to
+    /// apply outside string mode, branches need one on the control stack even though `branch_1`
+    /// does not.
+    pub(crate) fn requirements(self, reverse_mode: bool) -> (usize, usize) {
+        match self.c {
+            POP | TRANSFER_TOP_DATA_CONTROL | INCREMENT | DECREMENT | NOT | DUPLICATE => (1, 0),
+            WRITE if !reverse_mode => (1, 0),
+            READ if reverse_mode => (1, 0),
+            INTERCHANGE_TOPS => (1, 1),
+            TRANSFER_TOP_CONTROL_DATA | CONTROL_TOGGLE => (0, 1),
+            BRANCH_EAST | BRANCH_WEST | BRANCH_SOUTH | BRANCH_NORTH => (0, 1),
+            ADD | SUBTRACT | DIVIDE | XOR | ROTATE_LEFT | ROTATE_RIGHT | SWAP_TWO_TOP | OVER
+            | UNDUPLICATE => (2, 0),
+            EQUAL | LESS | GREATER => (2, 1),
+            MULTIPLY | AND | OR | DIG | BURY | SWAP_FIRST_THIRD | SWAP_SECOND_THIRD | UNDER => {
+                (3, 0)
+            }
+            _ => (0, 0),
+        }
+    }
+
+    /// Determine why none of the rules of `execute` applied to this instruction in `state`.
+    fn failure<I: Io, C: Cell>(self, state: &State<I, C>) -> ExecutionErrorKind<C> {
+        use self::ExecutionErrorKind::*;
+        let data = &state.data_stack;
+        let underflow = |stack, required, found| StackUnderflow {
+            stack,
+            required,
+            found,
+        };
+
+        // with digits accumulated only digit_end can apply, which needs a value to xor them into
+        if !state.multi_digit_accumulator.is_empty() {
+            return underflow(StackKind::Data, 1, data.len());
+        }
+        // in string mode only string_pop can fail
+        if state.string_mode {
+            return match data.last() {
+                Some(found) => StringMismatch {
+                    expected: self.c,
+                    found,
+                },
+                None => underflow(StackKind::Data, 1, 0),
+            };
+        }
+
+        let (data_required, control_required) = self.requirements(state.reverse_mode);
+        if data.len() < data_required {
+            return underflow(StackKind::Data, data_required, data.len());
+        }
+        if state.control_stack.len() < control_required {
+            return underflow(
+                StackKind::Control,
+                control_required,
+                state.control_stack.len(),
+            );
+        }
+
+        let top = data.last();
+        let nth = |n| data.get(data.len() - n).expect("checked above");
+        match self.c {
+            POP => PopNonZero {
+                top: top.expect("checked above"),
+            },
+            WRITE => underflow(StackKind::Output, 1, 0),
+            DIVIDE => DivisionByZero,
+            UNDUPLICATE => UnduplicateMismatch {
+                top: nth(1),
+                second: nth(2),
+            },
+            UNDER => UnderMismatch {
+                top: nth(1),
+                third: nth(3),
+            },
+            HALT => HaltInReverse,
+            _ => UnknownInstruction,
+        }
+    }
+
+    pub fn execute<I: Io, C: Cell>(
+        self,
+        state: &mut State<I, C>,
+    ) -> Result<InstructionExecutionStatus, ExecutionError<C>> {
         //println!("executing {}", self);
         use self::InstructionExecutionStatus::*;
         match self.c {
             // nop
             NOP if !state.string_mode && state.multi_digit_accumulator.is_empty() => {
                 state.location = state.next();
-                Successful("\\textrm{nop}")
+                Ok(Successful(Rule::Nop))
             }
             // digit
-            _ if !state.string_mode && self.c.is_digit(10) && !state.reverse_mode => {
+            _ if !state.string_mode && self.c.is_ascii_digit() && !state.reverse_mode => {
                 state.multi_digit_accumulator.push(self.c);
 
                 state.location = state.next();
-                Successful("\\textrm{digit}")
+                Ok(Successful(Rule::Digit))
             }
             // digit_inv
-            _ if !state.string_mode && self.c.is_digit(10) && state.reverse_mode => {
+            _ if !state.string_mode && self.c.is_ascii_digit() && state.reverse_mode => {
                 state.multi_digit_accumulator.insert(0, self.c);
 
                 state.location = state.next();
-                Successful("\\textrm{digit}_{\\,\\textrm{inv}}")
+                Ok(Successful(Rule::DigitInv))
             }
             // digit_end
             _ if !state.string_mode
                 && !state.multi_digit_accumulator.is_empty()
-                && !self.c.is_digit(10)
+                && !self.c.is_ascii_digit()
                 && !state.data_stack.is_empty() =>
             {
                 let x = state.data_stack.pop().expect("non empty");
-                let n: u32 = state
-                    .multi_digit_accumulator
-                    .parse()
-                    .expect("should only be integers");
+                let n = C::from_digits(&state.multi_digit_accumulator);
                 state.multi_digit_accumulator.clear();
-                state.data_stack.push(x ^ n);
+                state.data_stack.push(x.xor(&n));
 
-                Successful("\\textrm{digit}_{\\,\\textrm{end}}")
+                Ok(Successful(Rule::DigitEnd))
             }
             // push
             PUSH if !state.string_mode && state.multi_digit_accumulator.is_empty() => {
-                state.data_stack.push(0);
+                state.data_stack.push(C::zero());
 
                 state.location = state.next();
-                Successful("\\textrm{push}")
+                Ok(Successful(Rule::Push))
             }
             // pop
             POP if !state.string_mode
                 && state.multi_digit_accumulator.is_empty()
                 && !state.data_stack.is_empty()
-                && state.data_stack.last().expect("len >= 1") == 0 =>
+                && state.data_stack.last().expect("len >= 1").is_zero() =>
             {
                 state.data_stack.pop().expect("non empty");
 
                 state.location = state.next();
-                Successful("\\textrm{pop}")
+                Ok(Successful(Rule::Pop))
             }
             // transfer_1
             TRANSFER_TOP_DATA_CONTROL
@@ -180,7 +594,7 @@ impl Instruction {
                 state.control_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{transfer_1}")
+                Ok(Successful(Rule::Transfer1))
             }
             // transfer_2
             TRANSFER_TOP_CONTROL_DATA
@@ -192,7 +606,7 @@ impl Instruction {
                 state.data_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{transfer_2}")
+                Ok(Successful(Rule::Transfer2))
             }
             // interchange
             INTERCHANGE_TOPS
@@ -207,27 +621,31 @@ impl Instruction {
                 state.control_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{interchange}")
+                Ok(Successful(Rule::Interchange))
             }
             // write
             WRITE
                 if !state.string_mode
                     && state.multi_digit_accumulator.is_empty()
-                    && !state.reverse_mode =>
+                    && !state.reverse_mode
+                    && !state.data_stack.is_empty() =>
             {
                 // try casting the top of the data stack to a char
                 let top = state.data_stack.pop().expect("non_empty");
-                match char::from_u32(top as u32) {
-                    Some(c) if c.is_ascii() => {
+                match top.to_char() {
+                    Some(c) => {
+                        if let Err(e) = state.io.write_char(c) {
+                            state.data_stack.push(top);
+                            return Err(self.error(state, ExecutionErrorKind::Io(e.kind())));
+                        }
                         state.output_stack.push(c);
-                        print!("{}", c);
 
                         state.location = state.next();
-                        Successful("\\textrm{write}")
+                        Ok(Successful(Rule::Write))
                     }
                     _ => {
-                        state.data_stack.push(top);
-                        Unsuccessful
+                        state.data_stack.push(top.clone());
+                        Err(self.error(state, ExecutionErrorKind::NonAsciiWrite { value: top }))
                     }
                 }
             }
@@ -241,9 +659,10 @@ impl Instruction {
                 // pop the top char of the output stack and push its ascii value on the data stack.
                 let c = state.output_stack.pop().expect("non_empty");
                 debug_assert!(c.is_ascii());
-                state.data_stack.push(c as u32);
+                state.data_stack.push(C::from_char(c));
 
-                Successful("\\textrm{unwrite}")
+                state.location = state.next();
+                Ok(Successful(Rule::Unwrite))
             }
             // read
             READ if !state.string_mode
@@ -252,15 +671,22 @@ impl Instruction {
             {
                 // FIXME: this currently sees every byte as a char.
 
-                // if input stack is empty read a char from stdin, otherwise pop an item from the
-                // input stack and use that
+                // if input stack is empty read a char from the io backend, otherwise pop an item
+                // from the input stack and use that
                 let c = if state.input_stack.is_empty() {
                     // read a single byte from input
-                    match io::stdin().bytes().next() {
-                        Some(Ok(byte)) if byte.is_ascii() && !byte.is_ascii_control() => {
-                            Some(u32::from(byte))
+                    match state.io.read_byte() {
+                        Ok(Some(byte)) if byte.is_ascii() && !byte.is_ascii_control() => {
+                            C::from_char(char::from(byte))
+                        }
+                        Ok(Some(byte)) => {
+                            let kind = ExecutionErrorKind::InvalidInput { byte };
+                            return Err(self.error(state, kind));
+                        }
+                        Ok(None) => {
+                            return Err(self.error(state, ExecutionErrorKind::InputExhausted))
                         }
-                        _ => None,
+                        Err(e) => return Err(self.error(state, ExecutionErrorKind::Io(e.kind()))),
                     }
                 } else {
                     // pop char from input stack
@@ -268,19 +694,13 @@ impl Instruction {
                     debug_assert!(c.is_ascii());
                     debug_assert!(!c.is_ascii_control());
 
-                    Some(u32::from(c))
+                    C::from_char(c)
                 };
 
-                // check if we have a character and push it to the data stack
-                match c {
-                    Some(c) => {
-                        state.data_stack.push(c);
+                state.data_stack.push(c);
 
-                        state.location = state.next();
-                        Successful("\\textrm{read}")
-                    }
-                    None => Unsuccessful,
-                }
+                state.location = state.next();
+                Ok(Successful(Rule::Read))
             }
             // unread
             READ if !state.string_mode
@@ -289,16 +709,16 @@ impl Instruction {
                 && !state.data_stack.is_empty() =>
             {
                 let top = state.data_stack.pop().expect("non empty");
-                match char::from_u32(top as u32) {
-                    Some(c) if c.is_ascii() => {
+                match top.to_char() {
+                    Some(c) if !c.is_ascii_control() => {
                         state.input_stack.push(c);
 
                         state.location = state.next();
-                        Successful("\\textrm{unread}")
+                        Ok(Successful(Rule::Unread))
                     }
                     _ => {
-                        state.data_stack.push(top);
-                        Unsuccessful
+                        state.data_stack.push(top.clone());
+                        Err(self.error(state, ExecutionErrorKind::NonAsciiUnread { value: top }))
                     }
                 }
             }
@@ -309,10 +729,10 @@ impl Instruction {
                     && !state.data_stack.is_empty() =>
             {
                 let x = state.data_stack.pop().expect("non empty");
-                state.data_stack.push(x + 1);
+                state.data_stack.push(x.increment());
 
                 state.location = state.next();
-                Successful("\\textrm{increment}")
+                Ok(Successful(Rule::Increment))
             }
             // decrement
             DECREMENT
@@ -321,10 +741,10 @@ impl Instruction {
                     && !state.data_stack.is_empty() =>
             {
                 let x = state.data_stack.pop().expect("non empty");
-                state.data_stack.push(x - 1);
+                state.data_stack.push(x.decrement());
 
                 state.location = state.next();
-                Successful("\\textrm{decrement}")
+                Ok(Successful(Rule::Decrement))
             }
             // add
             ADD if !state.string_mode
@@ -333,11 +753,11 @@ impl Instruction {
             {
                 let x = state.data_stack.pop().expect("len >= 2");
                 let y = state.data_stack.pop().expect("len >= 2");
-                state.data_stack.push(y + x);
+                state.data_stack.push(y.add(&x));
                 state.data_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{add}")
+                Ok(Successful(Rule::Add))
             }
             // subtract
             SUBTRACT
@@ -347,27 +767,28 @@ impl Instruction {
             {
                 let x = state.data_stack.pop().expect("len >= 2");
                 let y = state.data_stack.pop().expect("len >= 2");
-                state.data_stack.push(y - x);
+                state.data_stack.push(y.subtract(&x));
                 state.data_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{subtract}")
+                Ok(Successful(Rule::Subtract))
             }
             // divide
             DIVIDE
                 if !state.string_mode
                     && state.multi_digit_accumulator.is_empty()
                     && state.data_stack.len() >= 2
-                    && state.data_stack.last().expect("len >= 2") != 0 =>
+                    && !state.data_stack.last().expect("len >= 2").is_zero() =>
             {
                 let x = state.data_stack.pop().expect("len >= 2");
                 let y = state.data_stack.pop().expect("len >= 2");
-                state.data_stack.push(y / x);
-                state.data_stack.push(y % x);
+                let (quotient, remainder) = y.div_rem(&x);
+                state.data_stack.push(quotient);
+                state.data_stack.push(remainder);
                 state.data_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{divide}")
+                Ok(Successful(Rule::Divide))
             }
             // multiply
             MULTIPLY
@@ -378,11 +799,11 @@ impl Instruction {
                 let x = state.data_stack.pop().expect("len >= 3");
                 let y = state.data_stack.pop().expect("len >= 3");
                 let z = state.data_stack.pop().expect("len >= 3");
-                state.data_stack.push((z * x) + y);
+                state.data_stack.push(z.multiply_add(&x, &y));
                 state.data_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{multiply}")
+                Ok(Successful(Rule::Multiply))
             }
             // not
             NOT if !state.string_mode
@@ -390,10 +811,10 @@ impl Instruction {
                 && !state.data_stack.is_empty() =>
             {
                 let x = state.data_stack.pop().expect("non empty");
-                state.data_stack.push(!x);
+                state.data_stack.push(x.not());
 
                 state.location = state.next();
-                Successful("\\textrm{not}")
+                Ok(Successful(Rule::Not))
             }
             // and
             AND if !state.string_mode
@@ -403,12 +824,12 @@ impl Instruction {
                 let x = state.data_stack.pop().expect("len >= 3");
                 let y = state.data_stack.pop().expect("len >= 3");
                 let z = state.data_stack.pop().expect("len >= 3");
-                state.data_stack.push((x & y) ^ z);
+                state.data_stack.push(x.and(&y).xor(&z));
                 state.data_stack.push(y);
                 state.data_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{and}")
+                Ok(Successful(Rule::And))
             }
             // or
             OR if !state.string_mode
@@ -418,12 +839,12 @@ impl Instruction {
                 let x = state.data_stack.pop().expect("len >= 3");
                 let y = state.data_stack.pop().expect("len >= 3");
                 let z = state.data_stack.pop().expect("len >= 3");
-                state.data_stack.push((x | y) ^ z);
+                state.data_stack.push(x.or(&y).xor(&z));
                 state.data_stack.push(y);
                 state.data_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{or}")
+                Ok(Successful(Rule::Or))
             }
             // xor
             XOR if !state.string_mode
@@ -432,11 +853,11 @@ impl Instruction {
             {
                 let x = state.data_stack.pop().expect("len >= 2");
                 let y = state.data_stack.pop().expect("len >= 2");
-                state.data_stack.push(x ^ y);
+                state.data_stack.push(x.xor(&y));
                 state.data_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{xor}")
+                Ok(Successful(Rule::Xor))
             }
             // rotate_left
             ROTATE_LEFT
@@ -446,11 +867,20 @@ impl Instruction {
             {
                 let x = state.data_stack.pop().expect("len >= 2");
                 let y = state.data_stack.pop().expect("len >= 2");
-                state.data_stack.push(y.rotate_left(x));
-                state.data_stack.push(x);
+                match y.rotate_left(&x) {
+                    Some(rotated) => {
+                        state.data_stack.push(rotated);
+                        state.data_stack.push(x);
 
-                state.location = state.next();
-                Successful("\\textrm{rotate}_{\\,\\textrm{left}}")
+                        state.location = state.next();
+                        Ok(Successful(Rule::RotateLeft))
+                    }
+                    None => {
+                        state.data_stack.push(y);
+                        state.data_stack.push(x);
+                        Err(self.error(state, ExecutionErrorKind::UnboundedRotation))
+                    }
+                }
             }
             // rotate_right
             ROTATE_RIGHT
@@ -460,11 +890,20 @@ impl Instruction {
             {
                 let x = state.data_stack.pop().expect("len >= 2");
                 let y = state.data_stack.pop().expect("len >= 2");
-                state.data_stack.push(y.rotate_right(x));
-                state.data_stack.push(x);
+                match y.rotate_right(&x) {
+                    Some(rotated) => {
+                        state.data_stack.push(rotated);
+                        state.data_stack.push(x);
 
-                state.location = state.next();
-                Successful("\\textrm{}")
+                        state.location = state.next();
+                        Ok(Successful(Rule::RotateRight))
+                    }
+                    None => {
+                        state.data_stack.push(y);
+                        state.data_stack.push(x);
+                        Err(self.error(state, ExecutionErrorKind::UnboundedRotation))
+                    }
+                }
             }
             // toggle
             CONTROL_TOGGLE
@@ -473,10 +912,10 @@ impl Instruction {
                     && !state.control_stack.is_empty() =>
             {
                 let c = state.control_stack.pop().expect("non empty");
-                state.control_stack.push(c ^ 1);
+                state.control_stack.push(c.xor(&C::one()));
 
                 state.location = state.next();
-                Successful("\\textrm{toggle}")
+                Ok(Successful(Rule::Toggle))
             }
             // equal_true / equal_false
             EQUAL
@@ -495,12 +934,12 @@ impl Instruction {
                     .expect("len >= 2");
                 if y == x {
                     let c = state.control_stack.pop().expect("non empty");
-                    state.control_stack.push(c ^ 1);
+                    state.control_stack.push(c.xor(&C::one()));
                     state.location = state.next();
-                    Successful("\\textrm{equal}_{\\,\\textrm{true}}")
+                    Ok(Successful(Rule::EqualTrue))
                 } else {
                     state.location = state.next();
-                    Successful("\\textrm{equal}_{\\,\\textrm{false}}")
+                    Ok(Successful(Rule::EqualFalse))
                 }
             }
             // less_true / less_false
@@ -519,12 +958,12 @@ impl Instruction {
                     .expect("len >= 2");
                 if y < x {
                     let c = state.control_stack.pop().expect("non empty");
-                    state.control_stack.push(c ^ 1);
+                    state.control_stack.push(c.xor(&C::one()));
                     state.location = state.next();
-                    Successful("\\textrm{equal}_{\\,\\textrm{true}}")
+                    Ok(Successful(Rule::LessTrue))
                 } else {
                     state.location = state.next();
-                    Successful("\\textrm{equal}_{\\,\\textrm{false}}")
+                    Ok(Successful(Rule::LessFalse))
                 }
             }
             // greater_true / greater_false
@@ -544,12 +983,12 @@ impl Instruction {
                     .expect("len >= 2");
                 if y > x {
                     let c = state.control_stack.pop().expect("non empty");
-                    state.control_stack.push(c ^ 1);
+                    state.control_stack.push(c.xor(&C::one()));
                     state.location = state.next();
-                    Successful("\\textrm{greater}_{\\,\\textrm{true}}")
+                    Ok(Successful(Rule::GreaterTrue))
                 } else {
                     state.location = state.next();
-                    Successful("\\textrm{greater}_{\\,\\textrm{false}}")
+                    Ok(Successful(Rule::GreaterFalse))
                 }
             }
             // swap_1
@@ -564,7 +1003,7 @@ impl Instruction {
                 state.data_stack.push(y);
 
                 state.location = state.next();
-                Successful("\\textrm{swap}_{\\,\\textrm{1}}")
+                Ok(Successful(Rule::Swap1))
             }
             // dig
             DIG if !state.string_mode
@@ -579,7 +1018,7 @@ impl Instruction {
                 state.data_stack.push(z);
 
                 state.location = state.next();
-                Successful("\\textrm{dig}")
+                Ok(Successful(Rule::Dig))
             }
             // bury
             BURY if !state.string_mode
@@ -594,7 +1033,7 @@ impl Instruction {
                 state.data_stack.push(y);
 
                 state.location = state.next();
-                Successful("\\textrm{bury}")
+                Ok(Successful(Rule::Bury))
             }
             // swap_3
             SWAP_FIRST_THIRD
@@ -610,7 +1049,7 @@ impl Instruction {
                 state.data_stack.push(z);
 
                 state.location = state.next();
-                Successful("\\textrm{swap}_{\\,\\textrm{3}}")
+                Ok(Successful(Rule::Swap3))
             }
             // swap_2
             SWAP_SECOND_THIRD
@@ -626,7 +1065,7 @@ impl Instruction {
                 state.data_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{swap}_{\\,\\textrm{2}}")
+                Ok(Successful(Rule::Swap2))
             }
             // over
             OVER if !state.string_mode
@@ -635,12 +1074,12 @@ impl Instruction {
             {
                 let x = state.data_stack.pop().expect("len >= 2");
                 let y = state.data_stack.pop().expect("len >= 2");
-                state.data_stack.push(y);
+                state.data_stack.push(y.clone());
                 state.data_stack.push(x);
                 state.data_stack.push(y);
 
                 state.location = state.next();
-                Successful("\\textrm{over}")
+                Ok(Successful(Rule::Over))
             }
             // under
             UNDER
@@ -659,7 +1098,7 @@ impl Instruction {
                 state.data_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{under}")
+                Ok(Successful(Rule::Under))
             }
             // duplicate
             DUPLICATE
@@ -668,11 +1107,11 @@ impl Instruction {
                     && !state.data_stack.is_empty() =>
             {
                 let x = state.data_stack.pop().expect("non empty");
-                state.data_stack.push(x);
+                state.data_stack.push(x.clone());
                 state.data_stack.push(x);
 
                 state.location = state.next();
-                Successful("\\textrm{duplicate}")
+                Ok(Successful(Rule::Duplicate))
             }
             // unduplicate
             UNDUPLICATE
@@ -688,34 +1127,40 @@ impl Instruction {
                 state.data_stack.pop().expect("len >= 2");
 
                 state.location = state.next();
-                Successful("\\textrm{unduplicate}")
+                Ok(Successful(Rule::Unduplicate))
             }
             // string_toggle
             STRING_MODE if state.multi_digit_accumulator.is_empty() => {
                 state.string_mode = !state.string_mode;
 
                 state.location = state.next();
-                Successful("\\textrm{string}_{\\,\\textrm{toggle}}")
+                Ok(Successful(Rule::StringToggle))
             }
             // halt
-            HALT if !state.string_mode && state.multi_digit_accumulator.is_empty() => Halt,
+            HALT if !state.string_mode
+                && state.multi_digit_accumulator.is_empty()
+                && !state.reverse_mode =>
+            {
+                Ok(Halt)
+            }
             // mirror_1
             MIRROR_BACK if !state.string_mode && state.multi_digit_accumulator.is_empty() => {
                 state.direction = state.direction.mirror();
 
                 state.location = state.next();
-                Successful("\\textrm{mirror}_{\\,\\textrm{1}}")
+                Ok(Successful(Rule::Mirror1))
             }
             // mirror_2
             MIRROR_FORWARD if !state.string_mode && state.multi_digit_accumulator.is_empty() => {
                 state.direction = state.direction.mirror().opposite();
 
                 state.location = state.next();
-                Successful("\\textrm{mirror}_{\\,\\textrm{2}}")
+                Ok(Successful(Rule::Mirror2))
             }
             // branch_1
             BRANCH_EAST | BRANCH_NORTH | BRANCH_SOUTH | BRANCH_WEST
-                if !state.string_mode && state.multi_digit_accumulator.is_empty()
+                if !state.string_mode
+                    && state.multi_digit_accumulator.is_empty()
                     && (self.direction().expect("self is a branching instruction")
                         == state.direction.right()
                         || self.direction().expect("self is a branching instruction")
@@ -725,61 +1170,68 @@ impl Instruction {
                 let d = self.direction().expect("self is a branching instruction");
                 if d == state.direction.right() {
                     // right turn
-                    state
-                        .control_stack
-                        .push(if !state.reverse_mode { 1 } else { 0 });
+                    state.control_stack.push(if !state.reverse_mode {
+                        C::one()
+                    } else {
+                        C::zero()
+                    });
                 } else if d == state.direction.left() {
                     // left turn
-                    state
-                        .control_stack
-                        .push(if !state.reverse_mode { 0 } else { 1 });
+                    state.control_stack.push(if !state.reverse_mode {
+                        C::zero()
+                    } else {
+                        C::one()
+                    });
                 } else {
                     panic!("incoming direction should be side of instuction");
                 }
                 state.direction = d;
 
                 state.location = state.next();
-                Successful("\\textrm{branch}_{\\,\\textrm{1}}")
+                Ok(Successful(Rule::Branch1))
             }
             // branch_2
             BRANCH_EAST | BRANCH_NORTH | BRANCH_SOUTH | BRANCH_WEST
-                if !state.string_mode && state.multi_digit_accumulator.is_empty()
+                if !state.string_mode
+                    && state.multi_digit_accumulator.is_empty()
                     && state.direction
                         == self
                             .direction()
                             .expect("self is a branching instruction")
-                            .opposite() && !state.control_stack.is_empty() =>
+                            .opposite()
+                    && !state.control_stack.is_empty() =>
             {
                 // IP coming from the opposite direction
                 let c = state.control_stack.pop().expect("non empty");
                 // Turn left if c equals 0 (opposite in reverse mode), and turn right if 1 (opposite in reverse mode)
-                if (c == 0) ^ state.reverse_mode {
+                if c.is_zero() ^ state.reverse_mode {
                     state.direction = state.direction.left();
                 } else {
                     state.direction = state.direction.right();
                 }
 
                 state.location = state.next();
-                Successful("\\textrm{branch}_{\\,\\textrm{2}}")
+                Ok(Successful(Rule::Branch2))
             }
             // branch_3
             BRANCH_EAST | BRANCH_NORTH | BRANCH_SOUTH | BRANCH_WEST
                 if !state.string_mode
                     && state.multi_digit_accumulator.is_empty()
-                    && state.direction == self.direction().expect("self is a branching instruction")
+                    && state.direction
+                        == self.direction().expect("self is a branching instruction")
                     && !state.control_stack.is_empty() =>
             {
                 // IP coming from the same direction
                 let c = state.control_stack.pop().expect("non empty");
                 // toggle top of the control stack
-                state.control_stack.push(c ^ 1);
+                state.control_stack.push(c.xor(&C::one()));
                 // toggle reverse mode
                 state.reverse_mode = !state.reverse_mode;
                 // go in the opposite direction
                 state.direction = state.direction.opposite();
 
                 state.location = state.next();
-                Successful("\\textrm{branch}_{\\,\\textrm{3}}")
+                Ok(Successful(Rule::Branch3))
             }
             // string_push
             _ if state.string_mode
@@ -788,35 +1240,32 @@ impl Instruction {
             {
                 // if reverse mode is not enabled push the ascii value of the char to the
                 // stack
-                state.data_stack.push(self.c as u32);
+                state.data_stack.push(C::from_char(self.c));
 
                 state.location = state.next();
-                Successful("\\textrm{string}_{\\,\\textrm{push}}")
+                Ok(Successful(Rule::StringPush))
             }
             // string_pop
             _ if state.string_mode
                 && state.multi_digit_accumulator.is_empty()
                 && state.reverse_mode
                 && !state.data_stack.is_empty()
-                && state.data_stack.last().expect("non empty") == self.c as u32 =>
+                && state.data_stack.last().expect("non empty") == C::from_char(self.c) =>
             {
                 state.data_stack.pop();
 
                 state.location = state.next();
-                Successful("\\textrm{string}_{\\,\\textrm{pop}}")
+                Ok(Successful(Rule::StringPop))
             }
             // reverse
             REVERSE_MODE if !state.string_mode && state.multi_digit_accumulator.is_empty() => {
                 state.reverse_mode = !state.reverse_mode;
 
                 state.location = state.next();
-                Successful("\\textrm{reverse}")
+                Ok(Successful(Rule::Reverse))
             }
             // no rule found
-            _ => {
-                println!("No rule found");
-                Unsuccessful
-            }
+            _ => Err(self.error(state, self.failure(state))),
         }
     }
 }
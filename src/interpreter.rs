@@ -1,37 +1,189 @@
-use instruction::InstructionExecutionStatus;
-use program::Program;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use cell::Cell;
+use direction::Direction;
+use instruction::{ExecutionError, InstructionExecutionStatus};
+use io::Io;
+use rule::Rule;
+use stack::Stack;
 use state::State;
+use statistics::Statistics;
+use trace::Observer;
+
+/// Limits on how long `run_with_limits` lets a program run, none are set by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Limits {
+    /// Stop after this many steps.
+    pub max_steps: Option<usize>,
+    /// Stop after running for this long.
+    pub timeout: Option<Duration>,
+    /// Stop when the program is in a state it was in before, it will then loop forever.
+    ///
+    /// This uses Brent's algorithm: the state is saved after 1, 2, 4, 8, ... steps and compared
+    /// with every following state. A cycle is found within about twice the number of steps it
+    /// takes to enter it plus the cycle length.
+    pub detect_cycles: bool,
+}
+
+/// How a run with `Limits` ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    /// The program halted after this many steps.
+    Halted { steps: usize },
+    /// The maximum number of steps was taken without halting.
+    StepLimit { steps: usize },
+    /// The timeout passed after this many steps.
+    Timeout { steps: usize },
+    /// The state after `steps` steps is the state after `steps - length` steps, so the program
+    /// repeats the same `length` steps forever.
+    Cycle { steps: usize, length: usize },
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Outcome::Halted { steps } => write!(f, "halted after {} steps", steps),
+            Outcome::StepLimit { steps } => write!(f, "step limit of {} steps reached", steps),
+            Outcome::Timeout { steps } => write!(f, "timed out after {} steps", steps),
+            Outcome::Cycle { steps, length } => write!(
+                f,
+                "infinite loop detected, the state after {} steps repeats every {} steps",
+                steps - length,
+                length
+            ),
+        }
+    }
+}
 
-pub fn run(program: Program) {
-    println!("Running program:");
-    println!("{}", program);
+/// Everything that determines how a program continues.
+///
+/// Bytes read from the `Io` are not on any stack, so their number is included to tell apart a
+/// loop that consumes input from one that does not.
+struct Configuration<C: Cell> {
+    location: (usize, usize),
+    direction: Direction,
+    reverse_mode: bool,
+    string_mode: bool,
+    multi_digit_accumulator: String,
+    data_stack: Stack<C>,
+    control_stack: Stack<C>,
+    input_stack: Stack<char>,
+    output_stack: Stack<char>,
+    bytes_read: usize,
+}
 
-    let mut state = State::new(program);
+impl<C: Cell> Configuration<C> {
+    fn new<I: Io>(state: &State<I, C>, bytes_read: usize) -> Self {
+        Self {
+            location: state.location,
+            direction: state.direction,
+            reverse_mode: state.reverse_mode,
+            string_mode: state.string_mode,
+            multi_digit_accumulator: state.multi_digit_accumulator.clone(),
+            data_stack: state.data_stack.clone(),
+            control_stack: state.control_stack.clone(),
+            input_stack: state.input_stack.clone(),
+            output_stack: state.output_stack.clone(),
+            bytes_read,
+        }
+    }
 
+    /// Whether `state` is in this configuration, comparing the cheap fields first so this usually
+    /// fails before comparing any stack.
+    fn matches<I: Io>(&self, state: &State<I, C>, bytes_read: usize) -> bool {
+        self.location == state.location
+            && self.direction == state.direction
+            && self.reverse_mode == state.reverse_mode
+            && self.string_mode == state.string_mode
+            && self.bytes_read == bytes_read
+            && self.data_stack.len() == state.data_stack.len()
+            && self.control_stack.len() == state.control_stack.len()
+            && self.multi_digit_accumulator == state.multi_digit_accumulator
+            && self.data_stack == state.data_stack
+            && self.control_stack == state.control_stack
+            && self.input_stack == state.input_stack
+            && self.output_stack == state.output_stack
+    }
+}
+
+/// Run `state` until the program halts, notifying `observer` of every step.
+pub fn run<I: Io, C: Cell, O: Observer>(
+    state: &mut State<I, C>,
+    observer: &mut O,
+) -> Result<(), ExecutionError<C>> {
+    run_with_limits(state, observer, &Limits::default()).map(|_| ())
+}
+
+/// Like `run_with_limits`, also collecting `Statistics` about the run.
+///
+/// The statistics are returned even if the program gets stuck, they then cover the steps up to
+/// the one that got stuck.
+pub fn run_with_statistics<I: Io, C: Cell, O: Observer>(
+    state: &mut State<I, C>,
+    observer: &mut O,
+    limits: &Limits,
+) -> (Result<Outcome, ExecutionError<C>>, Statistics) {
+    let mut statistics = Statistics::new();
+    let result = run_with_limits(state, &mut (observer, &mut statistics), limits);
+    (result, statistics)
+}
+
+/// Run `state` until the program halts or one of the `limits` is reached, notifying `observer` of
+/// every step.
+pub fn run_with_limits<I: Io, C: Cell, O: Observer>(
+    state: &mut State<I, C>,
+    observer: &mut O,
+    limits: &Limits,
+) -> Result<Outcome, ExecutionError<C>> {
+    observer.start(state);
+
+    let start = Instant::now();
+    // the saved configuration and the step after which it was saved
+    let mut saved: Option<(Configuration<C>, usize)> = None;
+    let mut bytes_read = 0;
     let mut counter = 0;
     loop {
-        print!("{} & ", counter);
-        counter += 1;
-        println!("{}", state.latex_representation());
-        match state.instr().execute(&mut state) {
-            InstructionExecutionStatus::Successful(s) => {
-                println!("    \\Rightarrow_{{[{}]}} \\\\", s);
-                continue;
-            }
-            InstructionExecutionStatus::Unsuccessful => {
-                println!(
-                    "Unsuccessful execution of instruction {} at line {}, col {})",
-                    state.instr(),
-                    state.location.1 + 1,
-                    state.location.0 + 1
-                );
-                println!();
-                break;
+        if limits.detect_cycles {
+            match saved {
+                // the first repetition of a configuration is exactly one cycle later
+                Some((ref previous, step)) if previous.matches(state, bytes_read) => {
+                    return Ok(Outcome::Cycle {
+                        steps: counter,
+                        length: counter - step,
+                    })
+                }
+                _ if counter.is_power_of_two() || counter == 0 => {
+                    saved = Some((Configuration::new(state, bytes_read), counter))
+                }
+                _ => {}
             }
-            InstructionExecutionStatus::Halt => {
-                println!("Program halted");
-                break;
+        }
+        if limits.max_steps.is_some_and(|max| counter >= max) {
+            return Ok(Outcome::StepLimit { steps: counter });
+        }
+        if limits
+            .timeout
+            .is_some_and(|timeout| start.elapsed() >= timeout)
+        {
+            return Ok(Outcome::Timeout { steps: counter });
+        }
+
+        // a read with an empty input stack takes a byte from the io
+        let input_stack_was_empty = state.input_stack.is_empty();
+
+        observer.before_step(counter, state);
+        let result = state.step();
+        observer.after_step(counter, state, &result);
+
+        match result? {
+            InstructionExecutionStatus::Successful(rule) => {
+                if rule == Rule::Read && input_stack_was_empty {
+                    bytes_read += 1;
+                }
+                counter += 1;
             }
+            InstructionExecutionStatus::Halt => return Ok(Outcome::Halted { steps: counter }),
         }
     }
 }
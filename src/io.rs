@@ -0,0 +1,170 @@
+//! The input and output backends used by the `read` and `write` instructions.
+//!
+//! A `State` only talks to its backend when the `read` instruction finds the input stack empty
+//! and when the `write` instruction is executed in forward mode. The `unread` and `unwrite`
+//! instructions keep using the input and output stacks of the state, so the backend never has to
+//! take anything back.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Stdin, Stdout, Write};
+use std::path::Path;
+
+pub trait Io {
+    /// Read a single byte of input, returns `None` when the input is exhausted.
+    fn read_byte(&mut self) -> io::Result<Option<u8>>;
+
+    /// Write a single character of output.
+    fn write_char(&mut self, c: char) -> io::Result<()>;
+}
+
+impl<I: Io + ?Sized> Io for &mut I {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        (**self).read_byte()
+    }
+
+    fn write_char(&mut self, c: char) -> io::Result<()> {
+        (**self).write_char(c)
+    }
+}
+
+impl<I: Io + ?Sized> Io for Box<I> {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        (**self).read_byte()
+    }
+
+    fn write_char(&mut self, c: char) -> io::Result<()> {
+        (**self).write_char(c)
+    }
+}
+
+/// Reads from the standard input and writes to the standard output of the process.
+pub struct StdIo {
+    stdin: Stdin,
+    stdout: Stdout,
+}
+
+impl StdIo {
+    pub fn new() -> Self {
+        Self {
+            stdin: io::stdin(),
+            stdout: io::stdout(),
+        }
+    }
+}
+
+impl Default for StdIo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Io for StdIo {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        // make sure a prompt written by the program is visible before blocking on input
+        self.stdout.flush()?;
+        read_byte(&mut self.stdin)
+    }
+
+    fn write_char(&mut self, c: char) -> io::Result<()> {
+        write!(self.stdout, "{}", c)
+    }
+}
+
+/// Reads from an in-memory buffer and collects the output in a string.
+pub struct MemoryIo {
+    input: VecDeque<u8>,
+    output: String,
+}
+
+impl MemoryIo {
+    pub fn new(input: &[u8]) -> Self {
+        Self {
+            input: input.iter().cloned().collect(),
+            output: String::new(),
+        }
+    }
+
+    /// The input that has not been read yet.
+    pub fn remaining_input(&self) -> &VecDeque<u8> {
+        &self.input
+    }
+
+    /// Everything written so far.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    pub fn into_output(self) -> String {
+        self.output
+    }
+}
+
+impl Default for MemoryIo {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+
+impl Io for MemoryIo {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.input.pop_front())
+    }
+
+    fn write_char(&mut self, c: char) -> io::Result<()> {
+        self.output.push(c);
+        Ok(())
+    }
+}
+
+/// Reads from any reader and writes to any writer, for example files or sockets.
+pub struct StreamIo<R: Read, W: Write> {
+    reader: R,
+    writer: W,
+}
+
+impl<R: Read, W: Write> StreamIo<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader, writer }
+    }
+
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+}
+
+/// Reads from one file and writes to another.
+pub type FileIo = StreamIo<BufReader<File>, BufWriter<File>>;
+
+impl FileIo {
+    /// Open `input` for reading and create (or truncate) `output` for writing.
+    pub fn open<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> io::Result<Self> {
+        Ok(Self::new(
+            BufReader::new(File::open(input)?),
+            BufWriter::new(File::create(output)?),
+        ))
+    }
+}
+
+impl<R: Read, W: Write> Io for StreamIo<R, W> {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        self.writer.flush()?;
+        read_byte(&mut self.reader)
+    }
+
+    fn write_char(&mut self, c: char) -> io::Result<()> {
+        write!(self.writer, "{}", c)
+    }
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! An interpreter for the [Befreak language](http://tunes.org/~iepos/befreak.html).
+//!
+//! A program is parsed into a [`Program`](program/struct.Program.html), wrapped in a
+//! [`State`](state/struct.State.html) and then executed one instruction at a time with
+//! [`State::step`](state/struct.State.html#method.step), or until it halts with
+//! [`interpreter::run`](interpreter/fn.run.html).
+
+#[cfg(feature = "tui")]
+extern crate crossterm;
+#[cfg(feature = "bignum")]
+extern crate num_bigint;
+#[cfg(feature = "bignum")]
+extern crate num_traits;
+
+pub mod cell;
+pub mod cfg;
+pub mod compiled;
+pub mod condition;
+pub mod debugger;
+pub mod direction;
+pub mod heatmap;
+pub mod instruction;
+pub mod interpreter;
+pub mod io;
+pub mod pointer;
+pub mod program;
+pub mod reachability;
+pub mod rule;
+pub mod stack;
+pub mod state;
+pub mod statistics;
+pub mod superinstruction;
+pub mod trace;
+pub mod transpile;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod verify;
+
+pub use cell::Cell;
+pub use direction::Direction;
+pub use instruction::{ExecutionError, Instruction, InstructionExecutionStatus};
+pub use io::{FileIo, Io, MemoryIo, StdIo, StreamIo};
+pub use program::Program;
+pub use rule::Rule;
+pub use stack::Stack;
+pub use state::{State, StepBackError};
+pub use statistics::Statistics;
+pub use trace::{JsonTrace, LatexTrace, Observer, TextTrace};
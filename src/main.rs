@@ -1,38 +1,668 @@
-#![allow(dead_code)]
-
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::prelude::*;
+use std::io::{self, BufReader, BufWriter};
+use std::process;
+use std::time::Duration;
 
+extern crate befreak;
 #[macro_use]
 extern crate clap;
+#[cfg(feature = "bignum")]
+extern crate num_bigint;
 
-use clap::{App, Arg};
+use befreak::cfg::ControlFlowGraph;
+use befreak::compiled::CompiledProgram;
+use befreak::debugger::Debugger;
+use befreak::interpreter::{Limits, Outcome};
+use befreak::program::ParseError;
+use befreak::reachability::Reachability;
+use befreak::transpile::{self, CellType};
+use befreak::{
+    heatmap, interpreter, verify, Cell, Instruction, Io, JsonTrace, LatexTrace, Program, State,
+    Statistics, StreamIo, TextTrace,
+};
+#[cfg(feature = "tui")]
+use befreak::{tui, MemoryIo};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
 
-mod direction;
-mod instruction;
-mod interpreter;
-mod program;
-mod rule;
-mod stack;
-mod state;
+#[cfg(not(feature = "bignum"))]
+const CELL_TYPES: &[&str] = &["i32", "i64"];
+#[cfg(feature = "bignum")]
+const CELL_TYPES: &[&str] = &["i32", "i64", "bignum"];
 
 fn main() {
-    let matches = App::new("Befreak Interpreter")
+    let app = App::new("Befreak Interpreter")
         .version(crate_version!())
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("FILE")
                 .help("The program file to interpret")
                 .required(true),
         )
-        .get_matches();
+        .arg(
+            Arg::with_name("input")
+                .long("input")
+                .short("i")
+                .value_name("FILE")
+                .help("Read the input of the program from FILE instead of stdin"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .value_name("FILE")
+                .help("Write the output of the program to FILE instead of stdout"),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .value_name("FORMAT")
+                .possible_values(&["latex", "text", "json"])
+                .help("Write a trace of every step to stderr"),
+        )
+        .arg(
+            Arg::with_name("verify")
+                .long("verify")
+                .conflicts_with_all(&["trace", "detect-cycles", "stats", "heatmap", "heatmap-svg"])
+                .help("Run the program to halt and back again, checking it is reversible"),
+        )
+        .arg(
+            Arg::with_name("cell")
+                .long("cell")
+                .value_name("TYPE")
+                .possible_values(CELL_TYPES)
+                .default_value("i32")
+                .help("The type of the values on the stacks, arithmetic wraps around on overflow"),
+        )
+        .arg(
+            Arg::with_name("max-steps")
+                .long("max-steps")
+                .value_name("N")
+                .validator(|n| n.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Stop with exit status 2 if the program has not halted after N steps"),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .value_name("SECONDS")
+                .validator(|n| n.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Stop with exit status 2 if the program has not halted after SECONDS"),
+        )
+        .arg(
+            Arg::with_name("detect-cycles")
+                .long("detect-cycles")
+                .help("Stop with exit status 2 when the program repeats a state, it loops forever"),
+        )
+        .arg(
+            Arg::with_name("stats").long("stats").help(
+                "Write statistics about the run to stderr, like the number of steps per rule",
+            ),
+        )
+        .arg(
+            Arg::with_name("heatmap")
+                .long("heatmap")
+                .help("Write the program colored by how often each cell was executed to stderr"),
+        )
+        .arg(
+            Arg::with_name("heatmap-svg")
+                .long("heatmap-svg")
+                .value_name("FILE")
+                .help("Write the heatmap of the run as an SVG image to FILE"),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about(
+                    "Report instructions that can never be executed, without running the program",
+                )
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("The program file to check")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("directions")
+                        .long("directions")
+                        .help("List the directions every reachable instruction is entered in"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("cfg")
+                .about("Write the control-flow graph of a program to stdout in Graphviz DOT")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("The program file to show")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compile")
+                .about("Translate a program into a standalone Rust or C program")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("The program file to translate")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .value_name("FILE")
+                        .help("Write the translation to FILE instead of stdout"),
+                )
+                .arg(
+                    Arg::with_name("target")
+                        .long("target")
+                        .value_name("LANGUAGE")
+                        .possible_values(&["rust", "c"])
+                        .help(
+                            "The language to translate to, by default C if the output file ends \
+                             in .c and Rust otherwise",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("cell")
+                        .long("cell")
+                        .value_name("TYPE")
+                        .possible_values(&["i32", "i64"])
+                        .default_value("i32")
+                        .help("The type of the values on the stacks"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("debug")
+                .about("Step through a program interactively, commands are read from stdin")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("The program file to debug")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .short("i")
+                        .value_name("FILE")
+                        .help("Read the input of the program from FILE, by default it is empty"),
+                )
+                .arg(
+                    Arg::with_name("cell")
+                        .long("cell")
+                        .value_name("TYPE")
+                        .possible_values(CELL_TYPES)
+                        .default_value("i32")
+                        .help("The type of the values on the stacks"),
+                ),
+        );
+    #[cfg(feature = "tui")]
+    let app = app.subcommand(
+        SubCommand::with_name("tui")
+            .about("Animate a program full-screen, with keys to pause, step and change speed")
+            .arg(
+                Arg::with_name("FILE")
+                    .help("The program file to show")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("input")
+                    .long("input")
+                    .short("i")
+                    .value_name("FILE")
+                    .help("Read the input of the program from FILE, by default it is empty"),
+            )
+            .arg(
+                Arg::with_name("cell")
+                    .long("cell")
+                    .value_name("TYPE")
+                    .possible_values(CELL_TYPES)
+                    .default_value("i32")
+                    .help("The type of the values on the stacks"),
+            ),
+    );
+    let matches = app.get_matches();
+
+    if let Some(matches) = matches.subcommand_matches("check") {
+        check(
+            matches.value_of("FILE").expect("FILE is required"),
+            matches.is_present("directions"),
+        );
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("cfg") {
+        let program = load(matches.value_of("FILE").expect("FILE is required"));
+        print!("{}", ControlFlowGraph::new(&program).dot());
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("compile") {
+        let program = load(matches.value_of("FILE").expect("FILE is required"));
+        let cell = match matches.value_of("cell") {
+            Some("i64") => CellType::I64,
+            _ => CellType::I32,
+        };
+        let output = matches.value_of("output");
+        let c = match matches.value_of("target") {
+            Some(target) => target == "c",
+            None => output.is_some_and(|path| path.ends_with(".c")),
+        };
+        let source = if c {
+            transpile::c(&program, cell)
+        } else {
+            transpile::rust(&program, cell)
+        };
+        match output {
+            Some(path) => {
+                if let Err(e) = fs::write(path, source) {
+                    eprintln!("error: unable to write {}: {}", path, e);
+                    process::exit(1);
+                }
+            }
+            None => print!("{}", source),
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("debug") {
+        let program = load(matches.value_of("FILE").expect("FILE is required"));
+        // stdin holds the debugger commands, so the program only gets input from a file
+        let reader: Box<dyn Read> = match matches.value_of("input") {
+            Some(input) => Box::new(BufReader::new(open(input, File::open(input)))),
+            None => Box::new(io::empty()),
+        };
+        let writer: Box<dyn Write> = Box::new(io::stdout());
+        let io = StreamIo::new(reader, writer);
+        match matches.value_of("cell") {
+            Some("i64") => debug(State::<_, i64>::with_io(program, io)),
+            #[cfg(feature = "bignum")]
+            Some("bignum") => debug(State::<_, BigInt>::with_io(program, io)),
+            _ => debug(State::<_, i32>::with_io(program, io)),
+        }
+        return;
+    }
+
+    #[cfg(feature = "tui")]
+    {
+        if let Some(matches) = matches.subcommand_matches("tui") {
+            let program = load(matches.value_of("FILE").expect("FILE is required"));
+            // the keyboard controls the visualiser, so the program only gets input from a file
+            let input = match matches.value_of("input") {
+                Some(input) => {
+                    let mut bytes = Vec::new();
+                    if let Err(e) = open(input, File::open(input)).read_to_end(&mut bytes) {
+                        eprintln!("error: unable to read input: {}", e);
+                        process::exit(1);
+                    }
+                    bytes
+                }
+                None => Vec::new(),
+            };
+            let io = MemoryIo::new(&input);
+            let result = match matches.value_of("cell") {
+                Some("i64") => tui::run(State::<_, i64>::with_io(program, io)),
+                #[cfg(feature = "bignum")]
+                Some("bignum") => tui::run(State::<_, BigInt>::with_io(program, io)),
+                _ => tui::run(State::<_, i32>::with_io(program, io)),
+            };
+            if let Err(e) = result {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+            return;
+        }
+    }
+
+    let program = load(matches.value_of("FILE").expect("FILE is required"));
 
-    let file = matches.value_of("FILE").expect("FILE is required");
-    let mut file = File::open(file).unwrap(); // TODO
+    let mut reader: Box<dyn Read> = match matches.value_of("input") {
+        Some(input) => Box::new(BufReader::new(open(input, File::open(input)))),
+        None => Box::new(io::stdin()),
+    };
+    let writer: Box<dyn Write> = match matches.value_of("output") {
+        Some(output) => Box::new(BufWriter::new(open(output, File::create(output)))),
+        None => Box::new(io::stdout()),
+    };
+
+    if matches.is_present("verify") {
+        let mut input = Vec::new();
+        if let Err(e) = reader.read_to_end(&mut input) {
+            eprintln!("error: unable to read input: {}", e);
+            process::exit(1);
+        }
+        let limits = limits(&matches);
+        match matches.value_of("cell") {
+            Some("i64") => verify::<i64>(program, &input, &limits, writer),
+            #[cfg(feature = "bignum")]
+            Some("bignum") => verify::<BigInt>(program, &input, &limits, writer),
+            _ => verify::<i32>(program, &input, &limits, writer),
+        }
+        return;
+    }
+
+    let io = StreamIo::new(reader, writer);
+    let trace = matches.value_of("trace");
+    let reports = Reports {
+        stats: matches.is_present("stats"),
+        heatmap: matches.is_present("heatmap"),
+        heatmap_svg: matches.value_of("heatmap-svg"),
+    };
+    let limits = limits(&matches);
+    match matches.value_of("cell") {
+        Some("i64") => run(
+            State::<_, i64>::with_io(program, io),
+            trace,
+            &reports,
+            &limits,
+        ),
+        #[cfg(feature = "bignum")]
+        Some("bignum") => run(
+            State::<_, BigInt>::with_io(program, io),
+            trace,
+            &reports,
+            &limits,
+        ),
+        _ => run(
+            State::<_, i32>::with_io(program, io),
+            trace,
+            &reports,
+            &limits,
+        ),
+    }
+}
+
+/// The limits of a run set by `--max-steps`, `--timeout` and `--detect-cycles`.
+fn limits(matches: &ArgMatches) -> Limits {
+    Limits {
+        max_steps: value_t!(matches, "max-steps", usize).ok(),
+        timeout: value_t!(matches, "timeout", f64)
+            .ok()
+            .map(|seconds| Duration::from_millis((seconds * 1000.0) as u64)),
+        detect_cycles: matches.is_present("detect-cycles"),
+    }
+}
+
+/// Read and parse the program at `path`, exiting with a diagnostic if it is not a valid program.
+fn load(path: &str) -> Program {
+    load_with_source(path).0
+}
+
+/// Like `load`, also returning the source of the program.
+fn load_with_source(path: &str) -> (Program, String) {
+    let mut file = open(path, File::open(path));
 
     let mut file_content = String::new();
-    file.read_to_string(&mut file_content).unwrap(); // TODO
+    if let Err(e) = file.read_to_string(&mut file_content) {
+        eprintln!("error: unable to read {}: {}", path, e);
+        process::exit(1);
+    }
+
+    match file_content.parse::<Program>() {
+        Ok(program) => (program, file_content),
+        Err(e) => {
+            eprint!("{}", diagnostic(path, &file_content, &e));
+            process::exit(1);
+        }
+    }
+}
+
+/// Report the instructions of the program at `path` that can never be executed and where it may
+/// get stuck, exiting with status 1 if there is anything to warn about. With `directions` the
+/// directions every instruction can be reached in are written to stdout.
+fn check(path: &str, directions: bool) {
+    let (program, source) = load_with_source(path);
+    let reachability = Reachability::analyse(&program);
+    let instruction = |location| {
+        *program
+            .instruction_at(location)
+            .expect("location inside the program")
+    };
+    let mut warnings = 0;
+
+    // adjacent cells on a line are reported together
+    let mut runs: Vec<((usize, usize), usize)> = Vec::new();
+    for &(x, y) in reachability.unreachable() {
+        match runs.last_mut() {
+            Some(&mut ((start, line), ref mut width)) if line == y && start + *width == x => {
+                *width += 1
+            }
+            _ => runs.push(((x, y), 1)),
+        }
+    }
+    for ((x, y), width) in runs {
+        let text: String = (x..x + width)
+            .map(|x| instruction((x, y)).to_string())
+            .collect();
+        eprintln!("warning: `{}` can never be executed", text);
+        eprint!("{}", snippet(path, &source, (y + 1, x + 1), width));
+        warnings += 1;
+    }
+
+    // execution starts at the first start symbol, the others only make sense inside a string
+    let start_symbols = program.start_symbols();
+    for &(x, y) in start_symbols.iter().skip(1) {
+        let in_strings = reachability
+            .pointers
+            .iter()
+            .filter(|pointer| pointer.location == (x, y))
+            .all(|pointer| pointer.string_mode);
+        if !in_strings || !reachability.is_reachable((x, y)) {
+            let (first_x, first_y) = start_symbols[0];
+            eprintln!(
+                "warning: another start symbol, the program starts at the one on line {}, col {}",
+                first_y + 1,
+                first_x + 1
+            );
+            eprint!("{}", snippet(path, &source, (y + 1, x + 1), 1));
+            warnings += 1;
+        }
+    }
+
+    let mut stuck: Vec<(usize, usize)> = reachability.stuck().iter().map(|p| p.location).collect();
+    stuck.sort_by_key(|&(x, y)| (y, x));
+    stuck.dedup();
+    for (x, y) in stuck {
+        // branches are over-approximated, so this is often a path no run takes
+        if instruction((x, y)).is_halt() {
+            eprintln!("note: the start symbol may be reached in reverse mode, where it is stuck");
+        } else {
+            eprintln!(
+                "warning: `{}` is not an instruction, the program is stuck if it gets here",
+                instruction((x, y))
+            );
+            warnings += 1;
+        }
+        eprint!("{}", snippet(path, &source, (y + 1, x + 1), 1));
+    }
+
+    if !reachability.can_halt() {
+        eprintln!("warning: the program can never halt");
+        eprintln!(" --> {}", path);
+        warnings += 1;
+    }
+
+    if directions {
+        for y in 0..program.rows() {
+            for x in 0..program.cols() {
+                let entered = reachability.directions((x, y));
+                if instruction((x, y)) != Instruction::nop() && !entered.is_empty() {
+                    let entered: Vec<String> = entered.iter().map(|d| d.to_string()).collect();
+                    println!(
+                        "line {}, col {} `{}`: {}",
+                        y + 1,
+                        x + 1,
+                        instruction((x, y)),
+                        entered.join(" ")
+                    );
+                }
+            }
+        }
+    }
 
-    let program = program::Program::from_str(&file_content).unwrap(); // TODO
+    if warnings > 0 {
+        process::exit(1);
+    }
+}
+
+/// Run the debugger REPL on `state` until the user quits.
+fn debug<I: Io, C: Cell>(state: State<I, C>) {
+    let stdin = io::stdin();
+    let mut debugger = Debugger::new(state);
+    if let Err(e) = debugger.repl(stdin.lock(), io::stdout()) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// The reports about a run to write when it ends.
+struct Reports<'a> {
+    stats: bool,
+    heatmap: bool,
+    heatmap_svg: Option<&'a str>,
+}
+
+impl<'a> Reports<'a> {
+    /// Whether any report needs the statistics of the run.
+    fn any(&self) -> bool {
+        self.stats || self.heatmap || self.heatmap_svg.is_some()
+    }
+
+    fn write(&self, statistics: &Statistics) {
+        if self.stats {
+            eprint!("{}", statistics);
+        }
+        if self.heatmap {
+            eprintln!("{}", heatmap::ansi(statistics));
+        }
+        if let Some(path) = self.heatmap_svg {
+            if let Err(e) = fs::write(path, heatmap::svg(statistics)) {
+                eprintln!("error: unable to write {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+/// Run the program in `state`, exiting with an error message if it gets stuck or does not halt
+/// within the `limits`. The `reports` are written at the end, also when it does not halt.
+fn run<I: Io, C: Cell>(
+    mut state: State<I, C>,
+    trace: Option<&str>,
+    reports: &Reports,
+    limits: &Limits,
+) {
+    let mut statistics = if reports.any() {
+        Some(Statistics::new())
+    } else {
+        None
+    };
+    let result = match trace {
+        Some("latex") => interpreter::run_with_limits(
+            &mut state,
+            &mut (LatexTrace::new(io::stderr()), &mut statistics),
+            limits,
+        ),
+        Some("text") => interpreter::run_with_limits(
+            &mut state,
+            &mut (TextTrace::new(io::stderr()), &mut statistics),
+            limits,
+        ),
+        Some("json") => interpreter::run_with_limits(
+            &mut state,
+            &mut (JsonTrace::new(io::stderr()), &mut statistics),
+            limits,
+        ),
+        // nothing looks at the steps one by one, so the faster compiled form can run them
+        _ if statistics.is_none() && limits.timeout.is_none() && !limits.detect_cycles => {
+            let program = CompiledProgram::new(&state.program);
+            match limits.max_steps {
+                Some(max_steps) => program.run_with_max_steps(&mut state, max_steps),
+                None => program
+                    .run(&mut state)
+                    .map(|steps| Outcome::Halted { steps }),
+            }
+        }
+        _ => interpreter::run_with_limits(&mut state, &mut statistics, limits),
+    };
+    // flush the output before a possible exit
+    drop(state);
+    if let Some(statistics) = statistics {
+        reports.write(&statistics);
+    }
+    match result {
+        Ok(Outcome::Halted { .. }) => {}
+        Ok(outcome) => {
+            eprintln!("error: {}", outcome);
+            process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Check that the program runs back to its start, exiting with an error message if it does not.
+fn verify<C: Cell>(program: Program, input: &[u8], limits: &Limits, mut writer: Box<dyn Write>) {
+    match verify::verify_with_limits::<C>(program, input, limits) {
+        Ok(verified) => {
+            if let Err(e) = writer.write_all(verified.output.as_bytes()) {
+                eprintln!("error: unable to write output: {}", e);
+                process::exit(1);
+            }
+            eprintln!(
+                "program is reversible, {} steps forward and backward",
+                verified.steps
+            );
+        }
+        Err(e @ verify::VerificationError::Stopped(_)) => {
+            eprintln!("error: {}", e);
+            process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Unwrap the result of opening `path`, exiting with an error message if that failed.
+fn open(path: &str, file: io::Result<File>) -> File {
+    file.unwrap_or_else(|e| {
+        eprintln!("error: unable to open {}: {}", path, e);
+        process::exit(1);
+    })
+}
+
+/// Render a parse error as a rustc-style diagnostic with a caret pointing into the source line.
+fn diagnostic(path: &str, source: &str, error: &ParseError) -> String {
+    let mut s = format!("error: {}\n", error);
+    match error.position() {
+        Some((line, column)) => s.push_str(&snippet(path, source, (line, column), 1)),
+        None => s.push_str(&format!(" --> {}\n", path)),
+    }
+    s
+}
 
-    interpreter::run(program);
+/// Show the 1-based (line, column) position of `source` with carets under `width` characters.
+fn snippet(path: &str, source: &str, position: (usize, usize), width: usize) -> String {
+    let (line, column) = position;
+    let gutter = " ".repeat(line.to_string().len());
+    // replace control characters so they do not mess up the terminal
+    let source_line: String = source
+        .lines()
+        .nth(line - 1)
+        .expect("position points into the source")
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let mut s = format!("{}--> {}:{}:{}\n", gutter, path, line, column);
+    s.push_str(&format!("{} |\n", gutter));
+    s.push_str(&format!("{} | {}\n", line, source_line));
+    s.push_str(&format!(
+        "{} | {}{}\n",
+        gutter,
+        " ".repeat(column - 1),
+        "^".repeat(width)
+    ));
+    s
 }
@@ -0,0 +1,80 @@
+//! The instruction pointer on its own, without the stacks, for analysing programs without running
+//! them.
+
+use cell::Cell;
+use direction::Direction;
+use io::Io;
+use program::Program;
+use state::State;
+
+/// Where the instruction pointer is, where it is heading and its modes.
+///
+/// This determines which instruction runs next and how it moves the pointer, up to the choices
+/// that depend on the stacks.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Pointer {
+    pub location: (usize, usize),
+    pub direction: Direction,
+    pub reverse_mode: bool,
+    pub string_mode: bool,
+}
+
+/// Where the instruction pointer goes after executing an instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Flow {
+    /// It moves on, possibly turned or with a mode toggled.
+    Next(Pointer),
+    /// It turns depending on the popped top of the control stack, this is `branch_2`.
+    Branch { zero: Pointer, nonzero: Pointer },
+    /// The program halts.
+    Halt,
+    /// No rule applies whatever the stacks hold: an unknown instruction, or the start symbol in
+    /// reverse mode.
+    Stuck,
+}
+
+impl Pointer {
+    /// The pointer at the start of `program`, like `State::with_io`.
+    pub fn start(program: &Program) -> Self {
+        Pointer {
+            location: program.lookup(),
+            direction: Direction::East,
+            reverse_mode: false,
+            string_mode: false,
+        }
+    }
+
+    /// The pointer of `state`.
+    pub fn of<I: Io, C: Cell>(state: &State<I, C>) -> Self {
+        Pointer {
+            location: state.location,
+            direction: state.direction,
+            reverse_mode: state.reverse_mode,
+            string_mode: state.string_mode,
+        }
+    }
+
+    /// The pointer one cell further in its direction, wrapping around the edges of `program`.
+    pub fn advance(self, program: &Program) -> Self {
+        let (x, y) = self.location;
+        let (r, c) = (program.rows(), program.cols());
+        let location = match self.direction {
+            Direction::North => (x, (y + r - 1) % r),
+            Direction::East => ((x + 1) % c, y),
+            Direction::South => (x, (y + 1) % r),
+            Direction::West => ((x + c - 1) % c, y),
+        };
+        Pointer { location, ..self }
+    }
+
+    /// Where the instruction at the pointer sends it, assuming the instruction applies.
+    ///
+    /// Numbers do not matter: `digit_end` does not move and the instruction after a number then
+    /// flows as usual.
+    pub fn flow(self, program: &Program) -> Flow {
+        let instruction = program
+            .instruction_at(self.location)
+            .expect("pointer should be inside the program");
+        instruction.flow(self, program)
+    }
+}
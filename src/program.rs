@@ -1,7 +1,71 @@
+use std::error::Error;
 use std::fmt;
+use std::str::FromStr;
 
 use instruction::Instruction;
 
+/// The reasons a string can fail to parse as a `Program`.
+///
+/// Lines and columns are 1-based and count characters, not bytes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParseError {
+    /// The character is not part of the ASCII character set.
+    NonAscii {
+        line: usize,
+        column: usize,
+        character: char,
+    },
+    /// The character is an ASCII control character (other than a tab).
+    ControlCharacter {
+        line: usize,
+        column: usize,
+        character: char,
+    },
+    /// The program contains a tab, which has no well-defined width on the grid.
+    Tab { line: usize, column: usize },
+    /// The program does not contain a single instruction.
+    Empty,
+}
+
+impl ParseError {
+    /// The line and column the error points at, if it points at a specific character.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match *self {
+            ParseError::NonAscii { line, column, .. }
+            | ParseError::ControlCharacter { line, column, .. }
+            | ParseError::Tab { line, column } => Some((line, column)),
+            ParseError::Empty => None,
+        }
+    }
+
+    /// The offending character, if the error points at a specific character.
+    pub fn character(&self) -> Option<char> {
+        match *self {
+            ParseError::NonAscii { character, .. }
+            | ParseError::ControlCharacter { character, .. } => Some(character),
+            ParseError::Tab { .. } => Some('\t'),
+            ParseError::Empty => None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::NonAscii { character, .. } => {
+                write!(f, "non-ASCII character {:?} in program", character)
+            }
+            ParseError::ControlCharacter { character, .. } => {
+                write!(f, "control character {:?} in program", character)
+            }
+            ParseError::Tab { .. } => write!(f, "tab character in program"),
+            ParseError::Empty => write!(f, "program is empty"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
 pub struct Program {
     instructions: Vec<Vec<Instruction>>,
 }
@@ -13,21 +77,73 @@ impl Program {
         }
     }
 
-    pub fn from_str(s: &str) -> Option<Self> {
-        // TODO: make Result
-        // try converting the string to a 2d-vec of instructions
-        // if this fails return None
+    pub fn rows(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.instructions.first().map_or(0, |v| v.len())
+    }
+
+    pub fn instruction_at(&self, location: (usize, usize)) -> Option<&Instruction> {
+        let (x, y) = location;
+        self.instructions.get(y)?.get(x)
+    }
 
+    /// The locations of the start symbols `@` in reading order, execution starts at the first.
+    /// Any others are only instructions a string can push.
+    pub fn start_symbols(&self) -> Vec<(usize, usize)> {
+        let mut start_symbols = Vec::new();
+        for (y, row) in self.instructions.iter().enumerate() {
+            for (x, ins) in row.iter().enumerate() {
+                if ins.is_halt() {
+                    start_symbols.push((x, y));
+                }
+            }
+        }
+        start_symbols
+    }
+
+    // lookup
+    pub fn lookup(&self) -> (usize, usize) {
+        // If no start symbol occurs start at the top left
+        // otherwise start one symbol to the east of the first start symbol
+        let first = self
+            .instructions
+            .iter()
+            .enumerate()
+            .find_map(|(y, row)| row.iter().position(|ins| ins.is_halt()).map(|x| (x, y)));
+        first.map_or((0, 0), |(x, y)| ((x + 1) % self.cols(), y))
+    }
+}
+
+impl FromStr for Program {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // try converting the string to a 2d-vec of instructions
         let mut instructions = {
             let mut rows = Vec::new();
-            for (x, line) in s.lines().enumerate() {
+            for (y, line) in s.lines().enumerate() {
                 let mut row = Vec::new();
-                for (y, c) in line.chars().enumerate() {
+                for (x, c) in line.chars().enumerate() {
+                    let (line, column) = (y + 1, x + 1);
                     let i = match Instruction::from_char(c) {
                         Some(i) => i,
+                        None if c == '\t' => return Err(ParseError::Tab { line, column }),
+                        None if !c.is_ascii() => {
+                            return Err(ParseError::NonAscii {
+                                line,
+                                column,
+                                character: c,
+                            })
+                        }
                         None => {
-                            eprintln!("unable to parse \'{}\' at {}:{}", c, x, y);
-                            return None;
+                            return Err(ParseError::ControlCharacter {
+                                line,
+                                column,
+                                character: c,
+                            })
                         }
                     };
                     row.push(i);
@@ -46,6 +162,10 @@ impl Program {
                 .fold(0, |width, row| cmp::max(width, row.len()))
         };
 
+        if width == 0 {
+            return Err(ParseError::Empty);
+        }
+
         // pad rows that are not of length width
         for row in &mut instructions {
             for _ in 0..(width - row.len()) {
@@ -54,39 +174,15 @@ impl Program {
         }
 
         // assert that all rows are of equal length
-        debug_assert!(instructions.iter().all(|ref row| row.len() == width));
-
-        Some(Program { instructions })
-    }
-
-    pub fn rows(&self) -> usize {
-        self.instructions.len()
-    }
+        debug_assert!(instructions.iter().all(|row| row.len() == width));
 
-    pub fn cols(&self) -> usize {
-        self.instructions.first().map_or(0, |v| v.len())
+        Ok(Program { instructions })
     }
+}
 
-    pub fn instruction_at(&self, location: (usize, usize)) -> Option<&Instruction> {
-        let (x, y) = location;
-        self.instructions.get(y)?.get(x)
-    }
-
-    // lookup
-    pub fn lookup(&self) -> (usize, usize) {
-        // Loop through the program, bottom to top, left to right, looking for @ (the start symbol)
-        let mut last_halt = None;
-        for (y, row) in self.instructions.iter().enumerate() {
-            for (x, ins) in row.iter().enumerate() {
-                if ins.is_halt() {
-                    last_halt = Some((x, y));
-                }
-            }
-        }
-
-        // If no start symbol occurs start at the top left
-        // otherwise start one symbol to the east of the start symbol
-        last_halt.map_or((0, 0), |(x, y)| ((x + 1) % self.cols(), y))
+impl Default for Program {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -0,0 +1,111 @@
+//! Which parts of a program the instruction pointer can reach, found without running it.
+//!
+//! Every branch decision is treated as possible, so this over-approximates the cells a run can
+//! execute: a cell found unreachable is dead for every input, a reachable one may still never run.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use direction::Direction;
+use instruction::Instruction;
+use pointer::{Flow, Pointer};
+use program::Program;
+
+/// The result of exploring every pointer a program can reach from its start.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reachability {
+    /// Every pointer the program can reach, each with the instruction about to execute.
+    pub pointers: BTreeSet<Pointer>,
+    /// The directions the pointer moves in when it reaches each location.
+    entered: BTreeMap<(usize, usize), BTreeSet<Direction>>,
+    /// Non-space cells that are never reached, except the start symbol.
+    unreachable: Vec<(usize, usize)>,
+    /// Pointers at which no rule can apply.
+    stuck: Vec<Pointer>,
+    halts: bool,
+}
+
+impl Reachability {
+    pub fn analyse(program: &Program) -> Self {
+        let start = Pointer::start(program);
+        let mut pointers = BTreeSet::new();
+        let mut stuck = Vec::new();
+        let mut halts = false;
+        let mut todo = vec![start];
+        while let Some(pointer) = todo.pop() {
+            if !pointers.insert(pointer) {
+                continue;
+            }
+            match pointer.flow(program) {
+                Flow::Next(next) => todo.push(next),
+                Flow::Branch { zero, nonzero } => {
+                    todo.push(zero);
+                    todo.push(nonzero);
+                }
+                Flow::Halt => halts = true,
+                Flow::Stuck => stuck.push(pointer),
+            }
+        }
+
+        let mut entered: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+        for pointer in &pointers {
+            entered
+                .entry(pointer.location)
+                .or_default()
+                .insert(pointer.direction);
+        }
+
+        // the start symbol is east of the start, it only runs when the program halts
+        let (x, y) = start.location;
+        let start_symbol = ((x + program.cols() - 1) % program.cols(), y);
+        let mut unreachable = Vec::new();
+        for y in 0..program.rows() {
+            for x in 0..program.cols() {
+                let instruction = program.instruction_at((x, y)).expect("inside the program");
+                let is_start = (x, y) == start_symbol && instruction.is_halt();
+                if *instruction != Instruction::nop() && !is_start && !entered.contains_key(&(x, y))
+                {
+                    unreachable.push((x, y));
+                }
+            }
+        }
+        stuck.sort();
+
+        Reachability {
+            pointers,
+            entered,
+            unreachable,
+            stuck,
+            halts,
+        }
+    }
+
+    /// Whether the instruction pointer can reach the (x, y) location.
+    pub fn is_reachable(&self, location: (usize, usize)) -> bool {
+        self.entered.contains_key(&location)
+    }
+
+    /// The directions the instruction pointer can move in when it reaches the (x, y) location.
+    pub fn directions(&self, location: (usize, usize)) -> Vec<Direction> {
+        self.entered
+            .get(&location)
+            .map_or_else(Vec::new, |directions| directions.iter().cloned().collect())
+    }
+
+    /// The cells holding an instruction that can never be executed, line by line.
+    ///
+    /// Spaces are left out, and so is the start symbol: it only executes when the program halts,
+    /// see `can_halt`.
+    pub fn unreachable(&self) -> &[(usize, usize)] {
+        &self.unreachable
+    }
+
+    /// The pointers at which the program gets stuck whatever the stacks hold.
+    pub fn stuck(&self) -> &[Pointer] {
+        &self.stuck
+    }
+
+    /// Whether the instruction pointer can get back to a start symbol in forward mode.
+    pub fn can_halt(&self) -> bool {
+        self.halts
+    }
+}
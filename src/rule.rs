@@ -1,13 +1,27 @@
-#[derive(Clone, Copy, PartialEq)]
+use std::fmt;
+
+/// The rules of the operational semantics, every successful step applies exactly one of them.
+///
+/// Halting is not a rule: it ends execution, see `InstructionExecutionStatus::Halt`.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Rule {
     Nop,
     Digit,
     DigitInv,
     DigitEnd,
-    Addition,
-    Subtraction,
+    Push,
+    Pop,
+    Transfer1,
+    Transfer2,
+    Interchange,
+    Write,
+    Unwrite,
+    Read,
+    Unread,
     Increment,
     Decrement,
+    Add,
+    Subtract,
     Divide,
     Multiply,
     Not,
@@ -16,20 +30,22 @@ pub enum Rule {
     Xor,
     RotateLeft,
     RotateRight,
-    Push,
-    Pop,
-    Move1,
-    Move2,
-    Switch,
-    Duplicate,
-    Unduplicate,
+    Toggle,
+    EqualTrue,
+    EqualFalse,
+    LessTrue,
+    LessFalse,
+    GreaterTrue,
+    GreaterFalse,
     Swap1,
     Swap2,
+    Swap3,
     Dig,
     Bury,
-    Flip,
     Over,
     Under,
+    Duplicate,
+    Unduplicate,
     Reverse,
     StringToggle,
     StringPush,
@@ -39,52 +55,320 @@ pub enum Rule {
     Branch1,
     Branch2,
     Branch3,
-    Halt,
 }
 
 impl Rule {
-    pub fn char(self) -> char {
+    /// Every rule, in the order they are declared.
+    pub const ALL: &'static [Rule] = &[
+        Rule::Nop,
+        Rule::Digit,
+        Rule::DigitInv,
+        Rule::DigitEnd,
+        Rule::Push,
+        Rule::Pop,
+        Rule::Transfer1,
+        Rule::Transfer2,
+        Rule::Interchange,
+        Rule::Write,
+        Rule::Unwrite,
+        Rule::Read,
+        Rule::Unread,
+        Rule::Increment,
+        Rule::Decrement,
+        Rule::Add,
+        Rule::Subtract,
+        Rule::Divide,
+        Rule::Multiply,
+        Rule::Not,
+        Rule::And,
+        Rule::Or,
+        Rule::Xor,
+        Rule::RotateLeft,
+        Rule::RotateRight,
+        Rule::Toggle,
+        Rule::EqualTrue,
+        Rule::EqualFalse,
+        Rule::LessTrue,
+        Rule::LessFalse,
+        Rule::GreaterTrue,
+        Rule::GreaterFalse,
+        Rule::Swap1,
+        Rule::Swap2,
+        Rule::Swap3,
+        Rule::Dig,
+        Rule::Bury,
+        Rule::Over,
+        Rule::Under,
+        Rule::Duplicate,
+        Rule::Unduplicate,
+        Rule::Reverse,
+        Rule::StringToggle,
+        Rule::StringPush,
+        Rule::StringPop,
+        Rule::Mirror1,
+        Rule::Mirror2,
+        Rule::Branch1,
+        Rule::Branch2,
+        Rule::Branch3,
+    ];
+
+    /// The plain name of the rule, like `branch_1`.
+    pub fn name(self) -> &'static str {
+        self.info().0
+    }
+
+    /// The name of the rule as typeset in the semantics, like `\textrm{branch}_{\,\textrm{1}}`.
+    pub fn latex_name(self) -> &'static str {
+        self.info().1
+    }
+
+    /// A one-line description of what the rule does.
+    pub fn description(self) -> &'static str {
+        self.info().2
+    }
+
+    fn info(self) -> (&'static str, &'static str, &'static str) {
         match self {
-            Rule::Nop => ' ',
-            Rule::Digit => ' ',
-            Rule::DigitInv => ' ',
-            Rule::DigitEnd => ' ',
-            Rule::Addition => ' ',
-            Rule::Subtraction => ' ',
-            Rule::Increment => ' ',
-            Rule::Decrement => ' ',
-            Rule::Divide => ' ',
-            Rule::Multiply => ' ',
-            Rule::Not => ' ',
-            Rule::And => ' ',
-            Rule::Or => ' ',
-            Rule::Xor => ' ',
-            Rule::RotateLeft => ' ',
-            Rule::RotateRight => ' ',
-            Rule::Push => ' ',
-            Rule::Pop => ' ',
-            Rule::Move1 => ' ',
-            Rule::Move2 => ' ',
-            Rule::Switch => ' ',
-            Rule::Duplicate => ' ',
-            Rule::Unduplicate => ' ',
-            Rule::Swap1 => ' ',
-            Rule::Swap2 => ' ',
-            Rule::Dig => ' ',
-            Rule::Bury => ' ',
-            Rule::Flip => ' ',
-            Rule::Over => ' ',
-            Rule::Under => ' ',
-            Rule::Reverse => ' ',
-            Rule::StringToggle => ' ',
-            Rule::StringPush => ' ',
-            Rule::StringPop => ' ',
-            Rule::Mirror1 => ' ',
-            Rule::Mirror2 => ' ',
-            Rule::Branch1 => ' ',
-            Rule::Branch2 => ' ',
-            Rule::Branch3 => ' ',
-            Rule::Halt => ' ',
+            Rule::Nop => ("nop", "\\textrm{nop}", "do nothing"),
+            Rule::Digit => (
+                "digit",
+                "\\textrm{digit}",
+                "append the digit to the number being read",
+            ),
+            Rule::DigitInv => (
+                "digit_inv",
+                "\\textrm{digit}_{\\,\\textrm{inv}}",
+                "append the digit to the number being read backwards",
+            ),
+            Rule::DigitEnd => (
+                "digit_end",
+                "\\textrm{digit}_{\\,\\textrm{end}}",
+                "xor the number that was read onto the top of the data stack",
+            ),
+            Rule::Push => ("push", "\\textrm{push}", "push a zero onto the data stack"),
+            Rule::Pop => ("pop", "\\textrm{pop}", "pop a zero off the data stack"),
+            Rule::Transfer1 => (
+                "transfer_1",
+                "\\textrm{transfer}_{\\,\\textrm{1}}",
+                "move the top of the data stack to the control stack",
+            ),
+            Rule::Transfer2 => (
+                "transfer_2",
+                "\\textrm{transfer}_{\\,\\textrm{2}}",
+                "move the top of the control stack to the data stack",
+            ),
+            Rule::Interchange => (
+                "interchange",
+                "\\textrm{interchange}",
+                "exchange the tops of the data and control stacks",
+            ),
+            Rule::Write => (
+                "write",
+                "\\textrm{write}",
+                "pop the top of the data stack and write it as a character",
+            ),
+            Rule::Unwrite => (
+                "unwrite",
+                "\\textrm{unwrite}",
+                "take back the last character written and push it onto the data stack",
+            ),
+            Rule::Read => (
+                "read",
+                "\\textrm{read}",
+                "read a character and push it onto the data stack",
+            ),
+            Rule::Unread => (
+                "unread",
+                "\\textrm{unread}",
+                "pop the top of the data stack and put it back into the input",
+            ),
+            Rule::Increment => (
+                "increment",
+                "\\textrm{increment}",
+                "add one to the top of the data stack",
+            ),
+            Rule::Decrement => (
+                "decrement",
+                "\\textrm{decrement}",
+                "subtract one from the top of the data stack",
+            ),
+            Rule::Add => (
+                "add",
+                "\\textrm{add}",
+                "add the top of the data stack to the entry below it",
+            ),
+            Rule::Subtract => (
+                "subtract",
+                "\\textrm{subtract}",
+                "subtract the top of the data stack from the entry below it",
+            ),
+            Rule::Divide => (
+                "divide",
+                "\\textrm{divide}",
+                "replace the second entry by its quotient and remainder by the top entry",
+            ),
+            Rule::Multiply => (
+                "multiply",
+                "\\textrm{multiply}",
+                "add the product of the top two entries to the third entry",
+            ),
+            Rule::Not => (
+                "not",
+                "\\textrm{not}",
+                "invert the bits of the top of the data stack",
+            ),
+            Rule::And => (
+                "and",
+                "\\textrm{and}",
+                "xor the bitwise and of the top two entries onto the third entry",
+            ),
+            Rule::Or => (
+                "or",
+                "\\textrm{or}",
+                "xor the bitwise or of the top two entries onto the third entry",
+            ),
+            Rule::Xor => (
+                "xor",
+                "\\textrm{xor}",
+                "xor the top of the data stack onto the entry below it",
+            ),
+            Rule::RotateLeft => (
+                "rotate_left",
+                "\\textrm{rotate}_{\\,\\textrm{left}}",
+                "rotate the bits of the second entry left by the top entry",
+            ),
+            Rule::RotateRight => (
+                "rotate_right",
+                "\\textrm{rotate}_{\\,\\textrm{right}}",
+                "rotate the bits of the second entry right by the top entry",
+            ),
+            Rule::Toggle => (
+                "toggle",
+                "\\textrm{toggle}",
+                "toggle the lowest bit of the top of the control stack",
+            ),
+            Rule::EqualTrue => (
+                "equal_true",
+                "\\textrm{equal}_{\\,\\textrm{true}}",
+                "the top two entries are equal, toggle the top of the control stack",
+            ),
+            Rule::EqualFalse => (
+                "equal_false",
+                "\\textrm{equal}_{\\,\\textrm{false}}",
+                "the top two entries are not equal, do nothing",
+            ),
+            Rule::LessTrue => (
+                "less_true",
+                "\\textrm{less}_{\\,\\textrm{true}}",
+                "the second entry is less than the top, toggle the top of the control stack",
+            ),
+            Rule::LessFalse => (
+                "less_false",
+                "\\textrm{less}_{\\,\\textrm{false}}",
+                "the second entry is not less than the top, do nothing",
+            ),
+            Rule::GreaterTrue => (
+                "greater_true",
+                "\\textrm{greater}_{\\,\\textrm{true}}",
+                "the second entry is greater than the top, toggle the top of the control stack",
+            ),
+            Rule::GreaterFalse => (
+                "greater_false",
+                "\\textrm{greater}_{\\,\\textrm{false}}",
+                "the second entry is not greater than the top, do nothing",
+            ),
+            Rule::Swap1 => (
+                "swap_1",
+                "\\textrm{swap}_{\\,\\textrm{1}}",
+                "swap the top two entries of the data stack",
+            ),
+            Rule::Swap2 => (
+                "swap_2",
+                "\\textrm{swap}_{\\,\\textrm{2}}",
+                "swap the second and third entries of the data stack",
+            ),
+            Rule::Swap3 => (
+                "swap_3",
+                "\\textrm{swap}_{\\,\\textrm{3}}",
+                "swap the first and third entries of the data stack",
+            ),
+            Rule::Dig => (
+                "dig",
+                "\\textrm{dig}",
+                "move the third entry of the data stack to the top",
+            ),
+            Rule::Bury => (
+                "bury",
+                "\\textrm{bury}",
+                "move the top of the data stack down to the third entry",
+            ),
+            Rule::Over => (
+                "over",
+                "\\textrm{over}",
+                "push a copy of the second entry of the data stack",
+            ),
+            Rule::Under => (
+                "under",
+                "\\textrm{under}",
+                "pop the top of the data stack, a copy of the third entry",
+            ),
+            Rule::Duplicate => (
+                "duplicate",
+                "\\textrm{duplicate}",
+                "push a copy of the top of the data stack",
+            ),
+            Rule::Unduplicate => (
+                "unduplicate",
+                "\\textrm{unduplicate}",
+                "pop the top of the data stack, a copy of the entry below it",
+            ),
+            Rule::Reverse => ("reverse", "\\textrm{reverse}", "toggle reverse mode"),
+            Rule::StringToggle => (
+                "string_toggle",
+                "\\textrm{string}_{\\,\\textrm{toggle}}",
+                "toggle string mode",
+            ),
+            Rule::StringPush => (
+                "string_push",
+                "\\textrm{string}_{\\,\\textrm{push}}",
+                "push the character onto the data stack",
+            ),
+            Rule::StringPop => (
+                "string_pop",
+                "\\textrm{string}_{\\,\\textrm{pop}}",
+                "pop the character off the data stack",
+            ),
+            Rule::Mirror1 => (
+                "mirror_1",
+                "\\textrm{mirror}_{\\,\\textrm{1}}",
+                "reflect off a `\\` mirror",
+            ),
+            Rule::Mirror2 => (
+                "mirror_2",
+                "\\textrm{mirror}_{\\,\\textrm{2}}",
+                "reflect off a `/` mirror",
+            ),
+            Rule::Branch1 => (
+                "branch_1",
+                "\\textrm{branch}_{\\,\\textrm{1}}",
+                "enter a branch from the side, push the turn taken onto the control stack",
+            ),
+            Rule::Branch2 => (
+                "branch_2",
+                "\\textrm{branch}_{\\,\\textrm{2}}",
+                "enter a branch from the front, turn as the popped top of the control stack says",
+            ),
+            Rule::Branch3 => (
+                "branch_3",
+                "\\textrm{branch}_{\\,\\textrm{3}}",
+                "enter a branch from behind, toggle the control stack and reverse mode, turn around",
+            ),
         }
     }
 }
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
@@ -1,11 +1,12 @@
 use std::fmt;
 use std::fmt::Display;
 
-pub struct Stack<T: Copy + Display> {
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Stack<T: Clone + Display> {
     s: Vec<T>,
 }
 
-impl<T: Copy + Display> Stack<T> {
+impl<T: Clone + Display> Stack<T> {
     pub fn new() -> Self {
         Stack { s: Vec::new() }
     }
@@ -30,10 +31,7 @@ impl<T: Copy + Display> Stack<T> {
     }
 
     pub fn last(&self) -> Option<T> {
-        match self.s.last() {
-            Some(&x) => Some(x),
-            None => None,
-        }
+        self.s.last().cloned()
     }
 
     pub fn len(&self) -> usize {
@@ -41,10 +39,7 @@ impl<T: Copy + Display> Stack<T> {
     }
 
     pub fn get(&self, index: usize) -> Option<T> {
-        match self.s.get(index) {
-            Some(&x) => Some(x),
-            None => None,
-        }
+        self.s.get(index).cloned()
     }
     pub fn latex_representation(&self) -> String {
         if self.s.is_empty() {
@@ -66,7 +61,13 @@ impl<T: Copy + Display> Stack<T> {
     }
 }
 
-impl<T: Copy + Display> fmt::Display for Stack<T> {
+impl<T: Clone + Display> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + Display> fmt::Display for Stack<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.s.is_empty() {
             write!(f, "ε")
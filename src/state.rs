@@ -1,14 +1,46 @@
+use std::error::Error;
 use std::fmt;
 
+use cell::Cell;
 use direction::Direction;
-use instruction::Instruction;
+use instruction::{ExecutionError, Instruction, InstructionExecutionStatus};
+use io::{Io, StdIo};
 use program::Program;
+use rule::Rule;
 use stack::Stack;
 
-pub struct State {
+/// The reasons `State::step_back` can not undo the previous step.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StepBackError<C: Cell = i32> {
+    /// The state is at the start of the program, there is no previous step.
+    AtStart,
+    /// The previous step added a digit to the accumulator, which can only be undone together with
+    /// the rest of the number.
+    InsideNumber,
+    /// The previous step xor-ed a number onto the data stack without moving, so the instruction
+    /// behind the current one was not the last one executed.
+    AfterNumber,
+    /// The inverse of the previous instruction does not apply to the state.
+    Stuck(ExecutionError<C>),
+}
+
+impl<C: Cell> fmt::Display for StepBackError<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StepBackError::AtStart => write!(f, "at the start of the program"),
+            StepBackError::InsideNumber => write!(f, "can not step back into a number"),
+            StepBackError::AfterNumber => write!(f, "can not step back over the end of a number"),
+            StepBackError::Stuck(e) => write!(f, "can not undo the previous step: {}", e),
+        }
+    }
+}
+
+impl<C: Cell> Error for StepBackError<C> {}
+
+pub struct State<I: Io = StdIo, C: Cell = i32> {
     pub program: Program,
-    pub data_stack: Stack<u32>,
-    pub control_stack: Stack<u32>,
+    pub data_stack: Stack<C>,
+    pub control_stack: Stack<C>,
     pub location: (usize, usize),
     pub direction: Direction,
     pub reverse_mode: bool,
@@ -16,16 +48,19 @@ pub struct State {
     pub multi_digit_accumulator: String,
     pub output_stack: Stack<char>,
     pub input_stack: Stack<char>,
+    pub io: I,
 }
 
-impl State {
+impl State<StdIo, i32> {
+    /// Create a state with 32-bit cells that reads from stdin and writes to stdout.
     pub fn new(program: Program) -> Self {
+        Self::with_io(program, StdIo::new())
+    }
+}
+
+impl<I: Io, C: Cell> State<I, C> {
+    pub fn with_io(program: Program, io: I) -> Self {
         let location = program.lookup();
-        println!(
-            "starting at location line {}, col {})",
-            location.1 + 1,
-            location.0 + 1
-        );
         Self {
             program,
             data_stack: Stack::new(),
@@ -37,6 +72,7 @@ impl State {
             multi_digit_accumulator: String::new(),
             output_stack: Stack::new(),
             input_stack: Stack::new(),
+            io,
         }
     }
 
@@ -51,10 +87,10 @@ impl State {
         let modulo = |a: isize, b: usize| ((a % b as isize) + b as isize) as usize % b;
 
         match self.direction {
-            Direction::North => (x, modulo((y - 1) as isize, r)),
+            Direction::North => (x, modulo(y as isize - 1, r)),
             Direction::East => ((x + 1) % c, y),
             Direction::South => (x, (y + 1) % r),
-            Direction::West => (modulo((x - 1) as isize, c), y),
+            Direction::West => (modulo(x as isize - 1, c), y),
         }
     }
 
@@ -65,13 +101,89 @@ impl State {
             .instruction_at(self.location)
             .expect("location in state should always give an instruction.");
 
-        if self.reverse_mode {
+        // in string mode every character stands for itself, so it is pushed and popped unchanged
+        if self.reverse_mode && !self.string_mode {
             instruction.inv()
         } else {
             *instruction
         }
     }
 
+    /// Execute the instruction at the current location, updating the state accordingly.
+    pub fn step(&mut self) -> Result<InstructionExecutionStatus, ExecutionError<C>> {
+        self.instr().execute(self)
+    }
+
+    /// Undo the previous step by executing the inverse of the instruction behind the current one.
+    ///
+    /// No history is kept, the previous instruction is found by looking backwards from the current
+    /// location. This works for every rule except the ones involved in numbers: digits are only
+    /// reversible as a whole number, and `digit_end` does not move. Returns the rule applied
+    /// backwards, on error the state is left unchanged.
+    pub fn step_back(&mut self) -> Result<Rule, StepBackError<C>> {
+        if !self.multi_digit_accumulator.is_empty() {
+            return Err(StepBackError::InsideNumber);
+        }
+
+        if self.is_at_start() {
+            return Err(StepBackError::AtStart);
+        }
+
+        // the previous instruction is the one we came from
+        let (location, direction) = (self.location, self.direction);
+        self.direction = direction.opposite();
+        let previous = self.next();
+        let instruction = *self
+            .program
+            .instruction_at(previous)
+            .expect("location in state should always give an instruction.");
+        // the start symbol is never executed, so there is nothing to undo
+        if instruction.is_halt() && !self.string_mode {
+            self.direction = direction;
+            return Err(StepBackError::AtStart);
+        }
+        // executing a digit would have left the accumulator non-empty, so it was digit_end
+        if instruction.is_digit() && !self.string_mode {
+            self.direction = direction;
+            return Err(StepBackError::AfterNumber);
+        }
+
+        // execute it backwards
+        self.location = previous;
+        self.reverse_mode = !self.reverse_mode;
+        let rule = match self.step() {
+            Ok(InstructionExecutionStatus::Successful(rule)) => rule,
+            Ok(InstructionExecutionStatus::Halt) => {
+                unreachable!("halts are not executed backwards")
+            }
+            Err(e) => {
+                self.location = location;
+                self.direction = direction;
+                self.reverse_mode = !self.reverse_mode;
+                return Err(StepBackError::Stuck(e));
+            }
+        };
+
+        // stand on the instruction again, facing the way it was entered
+        self.location = previous;
+        self.direction = self.direction.opposite();
+        self.reverse_mode = !self.reverse_mode;
+        Ok(rule)
+    }
+
+    /// Whether the state is the one a program starts in: on the instruction after the start
+    /// symbol, facing east, with both modes off and nothing on the stacks.
+    fn is_at_start(&self) -> bool {
+        self.location == self.program.lookup()
+            && self.direction == Direction::East
+            && !self.reverse_mode
+            && !self.string_mode
+            && self.data_stack.is_empty()
+            && self.control_stack.is_empty()
+            && self.output_stack.is_empty()
+            && self.input_stack.is_empty()
+    }
+
     pub fn latex_representation(&self) -> String {
         format!(
             "\\langle P, {D}, {C}, ({lx}, {ly}), {d}, {r}, {s}, {n} \\rangle, &\n    where $P_{{({lx}, {ly})}} = $ `\\verb|{instr}|' &",
@@ -101,7 +213,7 @@ impl State {
     }
 }
 
-impl fmt::Display for State {
+impl<I: Io, C: Cell> fmt::Display for State<I, C> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // write!(f, "<{P}>", P = self.program)
         write!(
@@ -0,0 +1,172 @@
+//! Counts what happens during a run, to compare the efficiency of programs.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use cell::Cell;
+use instruction::InstructionExecutionStatus;
+use io::Io;
+use program::Program;
+use rule::Rule;
+use state::State;
+use trace::{Observer, StepResult};
+
+/// The number of cells listed in the report, the full counts are in `Statistics::cells`.
+const HOTTEST_CELLS: usize = 10;
+
+/// Statistics about the steps of a run, collected by observing it.
+///
+/// Only successful steps are counted, the final halt and a step that gets stuck are not. Except in
+/// `cells`, where the halt counts as executing the start symbol so it is not shown as unreachable.
+/// The time spent in forward and reverse mode is counted in steps, not measured.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Statistics {
+    /// The number of successful steps.
+    pub steps: usize,
+    /// The number of steps taken in reverse mode.
+    pub reverse_steps: usize,
+    /// How often each rule was applied, rules that were never applied are left out.
+    pub rules: BTreeMap<Rule, usize>,
+    /// How often the instruction at each location was executed, indexed as `cells[y][x]`.
+    pub cells: Vec<Vec<usize>>,
+    pub max_data_depth: usize,
+    pub max_control_depth: usize,
+    /// The number of times a branch turned based on the control stack, that is `branch_2`.
+    pub branch_decisions: usize,
+    /// The instructions of the program, to show them in the report.
+    program: Vec<Vec<char>>,
+    /// The location and mode of the current step.
+    location: (usize, usize),
+    reverse_mode: bool,
+}
+
+impl Statistics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of steps taken in forward mode.
+    pub fn forward_steps(&self) -> usize {
+        self.steps - self.reverse_steps
+    }
+
+    /// The number of times the instruction at the (x, y) location was executed.
+    pub fn hits(&self, location: (usize, usize)) -> usize {
+        let (x, y) = location;
+        self.cells
+            .get(y)
+            .and_then(|row| row.get(x))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    /// The instruction at the (x, y) location of the program that was run.
+    pub fn instruction_at(&self, location: (usize, usize)) -> char {
+        let (x, y) = location;
+        self.program[y][x]
+    }
+
+    fn hit(&mut self) {
+        let (x, y) = self.location;
+        if let Some(hits) = self.cells.get_mut(y).and_then(|row| row.get_mut(x)) {
+            *hits += 1;
+        }
+    }
+}
+
+fn grid(program: &Program) -> Vec<Vec<char>> {
+    program
+        .to_string()
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect()
+}
+
+impl Observer for Statistics {
+    fn start<I: Io, C: Cell>(&mut self, state: &State<I, C>) {
+        self.program = grid(&state.program);
+        self.cells = vec![vec![0; state.program.cols()]; state.program.rows()];
+        self.max_data_depth = self.max_data_depth.max(state.data_stack.len());
+        self.max_control_depth = self.max_control_depth.max(state.control_stack.len());
+    }
+
+    fn before_step<I: Io, C: Cell>(&mut self, _step: usize, state: &State<I, C>) {
+        self.location = state.location;
+        self.reverse_mode = state.reverse_mode;
+    }
+
+    fn after_step<I: Io, C: Cell>(
+        &mut self,
+        _step: usize,
+        state: &State<I, C>,
+        result: &StepResult<C>,
+    ) {
+        let rule = match *result {
+            Ok(InstructionExecutionStatus::Successful(rule)) => rule,
+            Ok(InstructionExecutionStatus::Halt) => return self.hit(),
+            Err(_) => return,
+        };
+
+        self.steps += 1;
+        if self.reverse_mode {
+            self.reverse_steps += 1;
+        }
+        *self.rules.entry(rule).or_insert(0) += 1;
+        self.hit();
+        self.max_data_depth = self.max_data_depth.max(state.data_stack.len());
+        self.max_control_depth = self.max_control_depth.max(state.control_stack.len());
+        if rule == Rule::Branch2 {
+            self.branch_decisions += 1;
+        }
+    }
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "steps:            {}", self.steps)?;
+        writeln!(f, "forward steps:    {}", self.forward_steps())?;
+        writeln!(f, "reverse steps:    {}", self.reverse_steps)?;
+        writeln!(
+            f,
+            "max stack depth:  {} data, {} control",
+            self.max_data_depth, self.max_control_depth
+        )?;
+        writeln!(f, "branch decisions: {}", self.branch_decisions)?;
+
+        // most applied first, ties in the order the rules are declared
+        let mut rules: Vec<(&Rule, &usize)> = self.rules.iter().collect();
+        rules.sort_by_key(|&(_, &count)| Reverse(count));
+        writeln!(f, "rules:")?;
+        for (rule, count) in rules {
+            writeln!(
+                f,
+                "  {:<14} {:>10}  {}",
+                rule.name(),
+                count,
+                rule.description()
+            )?;
+        }
+
+        let mut cells: Vec<((usize, usize), usize)> = self
+            .cells
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, &n)| ((x, y), n)))
+            .filter(|&(_, n)| n > 0)
+            .collect();
+        cells.sort_by_key(|&(_, n)| Reverse(n));
+        writeln!(f, "hottest cells:")?;
+        for &((x, y), n) in cells.iter().take(HOTTEST_CELLS) {
+            writeln!(
+                f,
+                "  line {:>3}, col {:>3} `{}` {:>10}",
+                y + 1,
+                x + 1,
+                self.instruction_at((x, y)),
+                n
+            )?;
+        }
+        Ok(())
+    }
+}
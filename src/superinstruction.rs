@@ -0,0 +1,280 @@
+//! Superinstructions: straight-line runs of a program fused into a few operations.
+//!
+//! Where the instruction pointer has no choice, the instructions it executes next only depend on
+//! where it is, the direction it moves in and its modes. Such a run is a trace: moves, mirrors,
+//! `?` and spaces are folded into the operation before them, a run of `'` and `` ` `` becomes one
+//! addition, a number and its `digit_end` one xor, and a string literal one bulk push or pop. The
+//! amounts are cells, so a number is only parsed once, when its trace is built.
+//! `branch_1` always turns the same way on a trace, so it pushes a known value.
+//!
+//! Traces start where paths join, after a `branch_2` decision and after an instruction that is
+//! not fused, such as input and output. They end before another trace starts or an instruction
+//! that is not fused, and after a `branch_2` decision. Every operation checks up front whether its
+//! rules apply, when one does not the run carries on one step at a time from there.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use cell::Cell;
+use compiled::Opcode;
+use pointer::{Flow, Pointer};
+use program::Program;
+use reachability::Reachability;
+
+/// The most steps a trace takes, runs without a choice can be endless.
+const MAX_STEPS: usize = 1024;
+
+/// What an operation does to the stacks holding cells of type `C`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fused<C: Cell = i32> {
+    /// Nothing, the instruction pointer only moves.
+    Move,
+    /// Add this number to the top of the data stack: a run of `'` and `` ` ``.
+    Add(C),
+    /// Xor this number onto the top of the data stack: a number and its `digit_end`.
+    Xor(C),
+    /// Push these characters on the data stack: a string literal in forward mode.
+    PushString(Vec<char>),
+    /// Pop these characters off the data stack in this order: a string literal in reverse mode.
+    PopString(Vec<char>),
+    /// Push this value on the control stack: `branch_1`, which turns the same way every time.
+    PushControl(u8),
+    /// Toggle the top of the control stack: `branch_3`.
+    ToggleControl,
+    /// Execute the instruction at the start of the operation on its own.
+    Single(Opcode),
+}
+
+/// A fused operation of a trace.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Operation<C: Cell = i32> {
+    pub fused: Fused<C>,
+    /// The number of steps of the interpreter it replaces.
+    pub steps: usize,
+    /// The instruction pointer after the operation, none after `branch_2` where it depends on the
+    /// control stack.
+    pub end: Option<Pointer>,
+}
+
+/// The operations executed one after the other from the pointer a trace starts at.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trace<C: Cell = i32> {
+    pub operations: Vec<Operation<C>>,
+    /// The number of steps of the whole trace.
+    pub steps: usize,
+}
+
+impl<C: Cell> Default for Trace<C> {
+    fn default() -> Self {
+        Trace {
+            operations: Vec::new(),
+            steps: 0,
+        }
+    }
+}
+
+impl<C: Cell> Trace<C> {
+    fn push(&mut self, fused: Fused<C>, steps: usize, end: Option<Pointer>) {
+        self.steps += steps;
+        let merged = match (self.operations.last_mut(), &fused) {
+            (Some(last), Fused::Move) => Some(last),
+            (Some(last), Fused::PushString(chars)) => match last.fused {
+                Fused::PushString(ref mut string) => {
+                    string.extend(chars);
+                    Some(last)
+                }
+                _ => None,
+            },
+            (Some(last), Fused::PopString(chars)) => match last.fused {
+                Fused::PopString(ref mut string) => {
+                    string.extend(chars);
+                    Some(last)
+                }
+                _ => None,
+            },
+            (Some(last), Fused::Add(amount)) => match last.fused {
+                Fused::Add(ref mut sum) => {
+                    *sum = sum.add(amount);
+                    Some(last)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        match merged {
+            Some(last) => {
+                last.steps += steps;
+                last.end = end;
+            }
+            None => self.operations.push(Operation { fused, steps, end }),
+        }
+    }
+}
+
+/// The number at `pointer` and the pointer at its `digit_end`, with the number of digits.
+///
+/// Digits are read in the order the pointer passes them, in reverse mode each one is put in front.
+fn number(program: &Program, mut pointer: Pointer) -> Option<(String, usize, Pointer)> {
+    let mut digits = String::new();
+    loop {
+        let instruction = program
+            .instruction_at(pointer.location)
+            .expect("pointer should be inside the program");
+        if !instruction.is_digit() {
+            return Some((digits.clone(), digits.len(), pointer));
+        }
+        if digits.len() == MAX_STEPS {
+            return None;
+        }
+        let digit = instruction
+            .to_string()
+            .chars()
+            .next()
+            .expect("one character");
+        if pointer.reverse_mode {
+            digits.insert(0, digit);
+        } else {
+            digits.push(digit);
+        }
+        pointer = pointer.advance(program);
+    }
+}
+
+/// Build the trace from `start`. The pointers where following traces start are added to `heads`
+/// and, if they are new, to `todo`.
+fn trace<C: Cell>(
+    program: &Program,
+    start: Pointer,
+    heads: &mut BTreeSet<Pointer>,
+    todo: &mut Vec<Pointer>,
+) -> Trace<C> {
+    let mut trace = Trace::default();
+    let mut pointer = start;
+    loop {
+        if pointer != start && heads.contains(&pointer) {
+            return trace;
+        }
+        if trace.steps >= MAX_STEPS {
+            if heads.insert(pointer) {
+                todo.push(pointer);
+            }
+            return trace;
+        }
+        let instruction = *program
+            .instruction_at(pointer.location)
+            .expect("pointer should be inside the program");
+        let opcode = if pointer.reverse_mode {
+            instruction.inv().opcode()
+        } else {
+            instruction.opcode()
+        };
+        let next = match pointer.flow(program) {
+            Flow::Next(next) => next,
+            Flow::Branch { .. } => {
+                // both ways are heads already
+                trace.push(Fused::Single(opcode), 1, None);
+                return trace;
+            }
+            Flow::Halt | Flow::Stuck => return trace,
+        };
+
+        if pointer.string_mode {
+            let character = instruction
+                .to_string()
+                .chars()
+                .next()
+                .expect("one character");
+            let fused = if instruction.opcode() == Opcode::StringToggle {
+                Fused::Move
+            } else if pointer.reverse_mode {
+                Fused::PopString(vec![character])
+            } else {
+                Fused::PushString(vec![character])
+            };
+            trace.push(fused, 1, Some(next));
+            pointer = next;
+            continue;
+        }
+
+        if instruction.is_digit() {
+            match number(program, pointer) {
+                Some((digits, count, end)) if trace.steps + count < MAX_STEPS => {
+                    trace.push(Fused::Xor(C::from_digits(&digits)), count + 1, Some(end));
+                    pointer = end;
+                }
+                _ => return trace,
+            }
+            continue;
+        }
+
+        let fused = match opcode {
+            Opcode::Nop
+            | Opcode::Mirror1
+            | Opcode::Mirror2
+            | Opcode::Reverse
+            | Opcode::StringToggle => Fused::Move,
+            Opcode::Increment => Fused::Add(C::one()),
+            Opcode::Decrement => Fused::Add(C::zero().decrement()),
+            Opcode::Read
+            | Opcode::Write
+            | Opcode::RotateLeft
+            | Opcode::RotateRight
+            | Opcode::Interpret => {
+                if heads.insert(next) {
+                    todo.push(next);
+                }
+                return trace;
+            }
+            Opcode::Branch(branch) if branch == pointer.direction => Fused::ToggleControl,
+            Opcode::Branch(branch) => {
+                // a right turn pushes 1, in reverse mode 0
+                let right = branch == pointer.direction.right();
+                Fused::PushControl((right != pointer.reverse_mode) as u8)
+            }
+            opcode => Fused::Single(opcode),
+        };
+        trace.push(fused, 1, Some(next));
+        pointer = next;
+    }
+}
+
+/// The traces of `program`, by the pointer they start at.
+///
+/// Only the part of the program reachable from the start is traced.
+pub fn traces<C: Cell>(program: &Program) -> BTreeMap<Pointer, Trace<C>> {
+    let pointers = Reachability::analyse(program).pointers;
+
+    // a trace starts at the start, after branch_2 and where paths join
+    let mut heads = BTreeSet::new();
+    heads.insert(Pointer::start(program));
+    let mut predecessors: BTreeMap<Pointer, usize> = BTreeMap::new();
+    for &pointer in &pointers {
+        match pointer.flow(program) {
+            Flow::Next(next) => *predecessors.entry(next).or_insert(0) += 1,
+            Flow::Branch { zero, nonzero } => {
+                heads.insert(zero);
+                heads.insert(nonzero);
+            }
+            Flow::Halt | Flow::Stuck => {}
+        }
+    }
+    heads.extend(
+        predecessors
+            .iter()
+            .filter(|&(_, &n)| n > 1)
+            .map(|(&pointer, _)| pointer),
+    );
+
+    // tracing adds heads after instructions that are not fused
+    let mut traces = BTreeMap::new();
+    let mut todo: Vec<Pointer> = heads.iter().cloned().collect();
+    while let Some(head) = todo.pop() {
+        if traces.contains_key(&head) {
+            continue;
+        }
+        let trace = trace(program, head, &mut heads, &mut todo);
+        if !trace.operations.is_empty() {
+            traces.insert(head, trace);
+        }
+    }
+    traces
+}
@@ -0,0 +1,278 @@
+//! Observers that are notified before and after every step of `interpreter::run`.
+
+use std::io::Write;
+
+use cell::Cell;
+use instruction::{ExecutionError, InstructionExecutionStatus};
+use io::Io;
+use rule::Rule;
+use stack::Stack;
+use state::State;
+
+/// The result of executing a single step.
+pub type StepResult<C = i32> = Result<InstructionExecutionStatus, ExecutionError<C>>;
+
+/// Is notified of every step the interpreter takes.
+///
+/// All methods do nothing by default, so an observer only has to implement the hooks it is
+/// interested in. `()` is the observer that ignores everything.
+pub trait Observer {
+    /// Called once, before the first step is taken.
+    fn start<I: Io, C: Cell>(&mut self, _state: &State<I, C>) {}
+
+    /// Called before step number `step` (counting from 0) is executed.
+    fn before_step<I: Io, C: Cell>(&mut self, _step: usize, _state: &State<I, C>) {}
+
+    /// Called after step number `step` was executed, with the result of that step.
+    fn after_step<I: Io, C: Cell>(
+        &mut self,
+        _step: usize,
+        _state: &State<I, C>,
+        _result: &StepResult<C>,
+    ) {
+    }
+}
+
+impl Observer for () {}
+
+impl<O: Observer> Observer for &mut O {
+    fn start<I: Io, C: Cell>(&mut self, state: &State<I, C>) {
+        (**self).start(state)
+    }
+
+    fn before_step<I: Io, C: Cell>(&mut self, step: usize, state: &State<I, C>) {
+        (**self).before_step(step, state)
+    }
+
+    fn after_step<I: Io, C: Cell>(
+        &mut self,
+        step: usize,
+        state: &State<I, C>,
+        result: &StepResult<C>,
+    ) {
+        (**self).after_step(step, state, result)
+    }
+}
+
+/// Notifies the observer if there is one.
+impl<O: Observer> Observer for Option<O> {
+    fn start<I: Io, C: Cell>(&mut self, state: &State<I, C>) {
+        if let Some(observer) = self {
+            observer.start(state)
+        }
+    }
+
+    fn before_step<I: Io, C: Cell>(&mut self, step: usize, state: &State<I, C>) {
+        if let Some(observer) = self {
+            observer.before_step(step, state)
+        }
+    }
+
+    fn after_step<I: Io, C: Cell>(
+        &mut self,
+        step: usize,
+        state: &State<I, C>,
+        result: &StepResult<C>,
+    ) {
+        if let Some(observer) = self {
+            observer.after_step(step, state, result)
+        }
+    }
+}
+
+/// Notifies both observers, the first one first.
+impl<A: Observer, B: Observer> Observer for (A, B) {
+    fn start<I: Io, C: Cell>(&mut self, state: &State<I, C>) {
+        self.0.start(state);
+        self.1.start(state);
+    }
+
+    fn before_step<I: Io, C: Cell>(&mut self, step: usize, state: &State<I, C>) {
+        self.0.before_step(step, state);
+        self.1.before_step(step, state);
+    }
+
+    fn after_step<I: Io, C: Cell>(
+        &mut self,
+        step: usize,
+        state: &State<I, C>,
+        result: &StepResult<C>,
+    ) {
+        self.0.after_step(step, state, result);
+        self.1.after_step(step, state, result);
+    }
+}
+
+/// Writes every step as a row of a LaTeX table, the format used to typeset executions.
+pub struct LatexTrace<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> LatexTrace<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write> Observer for LatexTrace<W> {
+    fn start<I: Io, C: Cell>(&mut self, state: &State<I, C>) {
+        writeln!(self.writer, "Running program:").expect("unable to write trace");
+        writeln!(self.writer, "{}", state.program).expect("unable to write trace");
+        writeln!(
+            self.writer,
+            "starting at location line {}, col {})",
+            state.location.1 + 1,
+            state.location.0 + 1
+        )
+        .expect("unable to write trace");
+    }
+
+    fn before_step<I: Io, C: Cell>(&mut self, step: usize, state: &State<I, C>) {
+        writeln!(self.writer, "{} & {}", step, state.latex_representation())
+            .expect("unable to write trace");
+    }
+
+    fn after_step<I: Io, C: Cell>(
+        &mut self,
+        _step: usize,
+        _state: &State<I, C>,
+        result: &StepResult<C>,
+    ) {
+        match *result {
+            Ok(InstructionExecutionStatus::Successful(rule)) => writeln!(
+                self.writer,
+                "    \\Rightarrow_{{[{}]}} \\\\",
+                rule.latex_name()
+            ),
+            Ok(InstructionExecutionStatus::Halt) => writeln!(self.writer, "Program halted"),
+            Err(_) => Ok(()),
+        }
+        .expect("unable to write trace");
+    }
+}
+
+/// Writes every step as a line of plain text, using the `Display` implementation of `State`.
+pub struct TextTrace<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> TextTrace<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write> Observer for TextTrace<W> {
+    fn before_step<I: Io, C: Cell>(&mut self, step: usize, state: &State<I, C>) {
+        writeln!(self.writer, "{}: {} `{}`", step, state, state.instr())
+            .expect("unable to write trace");
+    }
+
+    fn after_step<I: Io, C: Cell>(
+        &mut self,
+        step: usize,
+        _state: &State<I, C>,
+        result: &StepResult<C>,
+    ) {
+        if let Ok(InstructionExecutionStatus::Halt) = *result {
+            writeln!(self.writer, "halted after {} steps", step).expect("unable to write trace");
+        }
+    }
+}
+
+/// Writes every step as a JSON object on its own line, for scripts that process executions.
+///
+/// Each object describes the state before the step and the rule that was applied, with the
+/// character read, written, unread or unwritten by `read`, `write`, `unread` and `unwrite`. A step
+/// that gets stuck has an `error` instead of a `rule`.
+pub struct JsonTrace<W: Write> {
+    writer: W,
+    /// The fields describing the state before the current step.
+    before: String,
+}
+
+impl<W: Write> JsonTrace<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            before: String::new(),
+        }
+    }
+}
+
+impl<W: Write> Observer for JsonTrace<W> {
+    fn before_step<I: Io, C: Cell>(&mut self, step: usize, state: &State<I, C>) {
+        let stack = |stack: &Stack<C>| {
+            let values: Vec<String> = (0..stack.len())
+                .filter_map(|i| stack.get(i))
+                .map(|value| value.to_string())
+                .collect();
+            values.join(",")
+        };
+        self.before = format!(
+            "\"step\":{},\"instruction\":{},\"line\":{},\"column\":{},\"direction\":\"{}\",\
+             \"data\":[{}],\"control\":[{}],\"reverse_mode\":{},\"string_mode\":{},\
+             \"accumulator\":{}",
+            step,
+            json_string(&state.instr().to_string()),
+            state.location.1 + 1,
+            state.location.0 + 1,
+            format!("{:?}", state.direction).to_lowercase(),
+            stack(&state.data_stack),
+            stack(&state.control_stack),
+            state.reverse_mode,
+            state.string_mode,
+            json_string(&state.multi_digit_accumulator)
+        );
+    }
+
+    fn after_step<I: Io, C: Cell>(
+        &mut self,
+        _step: usize,
+        state: &State<I, C>,
+        result: &StepResult<C>,
+    ) {
+        let result = match *result {
+            Ok(InstructionExecutionStatus::Successful(rule)) => {
+                let character = match rule {
+                    Rule::Read => Some(("read", state.data_stack.last().and_then(|c| c.to_char()))),
+                    Rule::Write => Some(("written", state.output_stack.last())),
+                    Rule::Unread => Some(("unread", state.input_stack.last())),
+                    Rule::Unwrite => Some((
+                        "unwritten",
+                        state.data_stack.last().and_then(|c| c.to_char()),
+                    )),
+                    _ => None,
+                };
+                match character {
+                    Some((key, Some(c))) => format!(
+                        "\"rule\":\"{}\",\"{}\":{}",
+                        rule,
+                        key,
+                        json_string(&c.to_string())
+                    ),
+                    _ => format!("\"rule\":\"{}\"", rule),
+                }
+            }
+            Ok(InstructionExecutionStatus::Halt) => "\"rule\":\"halt\"".to_owned(),
+            Err(ref e) => format!("\"error\":{}", json_string(&e.to_string())),
+        };
+        writeln!(self.writer, "{{{},{}}}", self.before, result).expect("unable to write trace");
+    }
+}
+
+/// Quote and escape `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
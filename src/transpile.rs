@@ -0,0 +1,648 @@
+//! Translating programs ahead of time into other languages.
+//!
+//! Every configuration of the instruction pointer reachable from the start, its location,
+//! direction and modes, becomes a state of a `StateMachine` with the instruction it executes
+//! resolved for those modes. Backends turn every state into a labelled piece of code that does what
+//! the interpreter does there and selects the next state, in a loop. Translated programs fail,
+//! and report it, exactly where the interpreter fails.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::iter;
+
+use compiled::Opcode;
+use instruction::Instruction;
+use pointer::{Flow, Pointer};
+use program::Program;
+use reachability::Reachability;
+
+/// The integer type of the cells of a translated program.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CellType {
+    I32,
+    I64,
+}
+
+/// What a state does, resolved for the modes of its pointer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Nothing, the pointer only moves or turns: spaces, mirrors, `?` and `"`.
+    Move,
+    /// Append the digit to the number being read.
+    Digit(u8),
+    StringPush(char),
+    StringPop(char),
+    /// `branch_1` pushes the turn it takes, 0 or 1.
+    PushControl(u8),
+    /// `branch_3` toggles the top of the control stack.
+    ToggleControl,
+    /// `branch_2` pops the control stack and goes to the first of the next states on zero, to the
+    /// second otherwise.
+    Branch,
+    /// Any other instruction, as the opcode it executes in the mode of the pointer.
+    Operation(Opcode),
+    Halt,
+    /// No rule can apply: an unknown instruction or the start symbol in reverse mode.
+    Stuck,
+}
+
+/// A state of a `StateMachine`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub pointer: Pointer,
+    /// The instruction at the location of the pointer.
+    pub instruction: Instruction,
+    pub action: Action,
+    /// Whether the pointer can arrive with a number read, which `digit_end` first xors onto the
+    /// data stack without moving.
+    pub digit_end: bool,
+    /// The indices of the states the pointer goes to next.
+    pub next: Vec<usize>,
+}
+
+impl Node {
+    /// The instruction executed, in reverse mode the inverse of the one in the program except in
+    /// string mode.
+    pub fn executed(&self) -> Instruction {
+        if self.pointer.reverse_mode && !self.pointer.string_mode {
+            self.instruction.inv()
+        } else {
+            self.instruction
+        }
+    }
+
+    /// Where an error happens in this state, as `befreak` puts it after the kind of error.
+    pub fn context(&self) -> String {
+        let pointer = &self.pointer;
+        let on_off = |mode| if mode { "on" } else { "off" };
+        format!(
+            " at line {}, col {} (instruction `{}`, direction {}, reverse mode {}, string mode {})",
+            pointer.location.1 + 1,
+            pointer.location.0 + 1,
+            self.executed(),
+            pointer.direction,
+            on_off(pointer.reverse_mode),
+            on_off(pointer.string_mode)
+        )
+    }
+
+    /// A description of the state for a comment.
+    fn describe(&self) -> String {
+        let mut s = format!(
+            "line {}, col {} `{}` {:?}",
+            self.pointer.location.1 + 1,
+            self.pointer.location.0 + 1,
+            self.instruction,
+            self.pointer.direction
+        );
+        if self.pointer.reverse_mode {
+            s.push_str(" reverse");
+        }
+        if self.pointer.string_mode {
+            s.push_str(" string");
+        }
+        s
+    }
+
+    /// The number of elements the data and control stack need before the state does anything.
+    fn requirements(&self) -> (usize, usize) {
+        match self.action {
+            Action::Operation(_) | Action::Stuck => {
+                self.executed().requirements(self.pointer.reverse_mode)
+            }
+            Action::Branch | Action::ToggleControl => (0, 1),
+            _ => (0, 0),
+        }
+    }
+
+    /// Why the state is stuck.
+    fn stuck(&self) -> &'static str {
+        if self.executed().is_halt() {
+            "reached the start symbol in reverse mode"
+        } else {
+            "unknown instruction"
+        }
+    }
+}
+
+/// The reachable configurations of a program as states, the first one is the start.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateMachine {
+    pub nodes: Vec<Node>,
+}
+
+fn action(pointer: Pointer, instruction: Instruction, flow: Flow) -> Action {
+    let character = instruction
+        .to_string()
+        .chars()
+        .next()
+        .expect("one character");
+    if pointer.string_mode {
+        return match instruction.opcode() {
+            Opcode::StringToggle => Action::Move,
+            _ if pointer.reverse_mode => Action::StringPop(character),
+            _ => Action::StringPush(character),
+        };
+    }
+    match flow {
+        Flow::Halt => return Action::Halt,
+        Flow::Stuck => return Action::Stuck,
+        Flow::Branch { .. } => return Action::Branch,
+        Flow::Next(_) => {}
+    }
+    if instruction.is_digit() {
+        return Action::Digit(character as u8 - b'0');
+    }
+    let opcode = if pointer.reverse_mode {
+        instruction.inv().opcode()
+    } else {
+        instruction.opcode()
+    };
+    match opcode {
+        Opcode::Nop
+        | Opcode::Mirror1
+        | Opcode::Mirror2
+        | Opcode::Reverse
+        | Opcode::StringToggle => Action::Move,
+        Opcode::Branch(branch) if branch == pointer.direction => Action::ToggleControl,
+        Opcode::Branch(branch) => {
+            // a right turn pushes 1, in reverse mode 0
+            let right = branch == pointer.direction.right();
+            Action::PushControl((right != pointer.reverse_mode) as u8)
+        }
+        opcode => Action::Operation(opcode),
+    }
+}
+
+impl StateMachine {
+    pub fn new(program: &Program) -> Self {
+        let start = Pointer::start(program);
+        let pointers = Reachability::analyse(program).pointers;
+        let order: Vec<Pointer> = iter::once(start)
+            .chain(pointers.into_iter().filter(|&pointer| pointer != start))
+            .collect();
+        let index: BTreeMap<Pointer, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(i, &pointer)| (pointer, i))
+            .collect();
+
+        let mut nodes: Vec<Node> = order
+            .iter()
+            .map(|&pointer| {
+                let instruction = *program
+                    .instruction_at(pointer.location)
+                    .expect("pointer should be inside the program");
+                let flow = pointer.flow(program);
+                let next = match flow {
+                    Flow::Next(next) => vec![index[&next]],
+                    Flow::Branch { zero, nonzero } => vec![index[&zero], index[&nonzero]],
+                    Flow::Halt | Flow::Stuck => vec![],
+                };
+                Node {
+                    pointer,
+                    instruction,
+                    action: action(pointer, instruction, flow),
+                    digit_end: false,
+                    next,
+                }
+            })
+            .collect();
+
+        // a number ends at the first state after a digit that is not a digit itself
+        let is_digit = |node: &Node| matches!(node.action, Action::Digit(_));
+        let ends: Vec<usize> = nodes
+            .iter()
+            .filter(|node| is_digit(node))
+            .flat_map(|node| node.next.iter().cloned())
+            .filter(|&next| !is_digit(&nodes[next]))
+            .collect();
+        for end in ends {
+            nodes[end].digit_end = true;
+        }
+
+        StateMachine { nodes }
+    }
+
+    /// Whether a reachable state unwrites, only then the output has to be kept to unwrite it.
+    pub fn unwrites(&self) -> bool {
+        self.nodes.iter().any(|node| {
+            node.pointer.reverse_mode && node.action == Action::Operation(Opcode::Write)
+        })
+    }
+}
+
+/// The source of a standalone Rust program that runs `program` like `befreak` does.
+///
+/// The program has no dependencies, `rustc` compiles it on its own.
+pub fn rust(program: &Program, cell: CellType) -> String {
+    let machine = StateMachine::new(program);
+    let mut s = String::new();
+    s.push_str("// Translated from Befreak by `befreak compile`.\n\n");
+    s.push_str("#![allow(dead_code, unreachable_code, unused_mut)]\n\n");
+    let cell = match cell {
+        CellType::I32 => "i32",
+        CellType::I64 => "i64",
+    };
+    writeln!(s, "type Cell = {};\n", cell).expect("writing to a string");
+    writeln!(s, "const RECORD_OUTPUT: bool = {};\n", machine.unwrites())
+        .expect("writing to a string");
+    s.push_str(include_str!("../runtime/befreak.rs"));
+    s.push_str("\nfn run(m: &mut Machine) {\n    let mut state = 0;\n    loop {\n");
+    s.push_str("        m.tick();\n        state = match state {\n");
+    for (i, node) in machine.nodes.iter().enumerate() {
+        writeln!(s, "            // {}", node.describe()).expect("writing to a string");
+        writeln!(s, "            {} => {{", i).expect("writing to a string");
+        for line in rust_state(node).lines() {
+            writeln!(s, "                {}", line).expect("writing to a string");
+        }
+        s.push_str("            }\n");
+    }
+    s.push_str("            _ => unreachable!(),\n        };\n    }\n}\n");
+    s
+}
+
+/// The statements of a state in Rust, ending with the expression for the next state.
+fn rust_state(node: &Node) -> String {
+    let at = format!("{:?}", node.context());
+    let mut s = String::new();
+    if node.digit_end {
+        writeln!(
+            s,
+            "if m.digits > 0 {{\n    m.digit_end({});\n    m.tick();\n}}",
+            at
+        )
+        .expect("writing to a string");
+    }
+    let (data, control) = node.requirements();
+    if data > 0 || control > 0 {
+        writeln!(s, "m.need({}, {}, {});", data, control, at).expect("writing to a string");
+    }
+    let fail = |kind: &str| format!("m.fail({}, {})", kind, at);
+    let code = match node.action {
+        Action::Move => String::new(),
+        Action::Digit(digit) => format!("m.digit({}, {});", digit, node.pointer.reverse_mode),
+        Action::StringPush(c) => format!("m.data.push({});", c as u32),
+        Action::StringPop(c) => format!(
+            "match m.data.last() {{\n    Some(&{}) => {{ m.data.pop(); }}\n    \
+             Some(&top) => {},\n    None => {},\n}}",
+            c as u32,
+            fail(&format!(
+                "format!(\"can not pop {{:?}} in string mode, top of the data stack is {{}}\", \
+                 {:?}, top)",
+                c
+            )),
+            fail("underflow(\"data stack\", 1, 0)")
+        ),
+        Action::PushControl(value) => format!("m.control.push({});", value),
+        Action::ToggleControl => "*m.control.last_mut().unwrap() ^= 1;".to_string(),
+        Action::Branch => {
+            return format!(
+                "{}if m.control.pop().unwrap() == 0 {{ {} }} else {{ {} }}\n",
+                s, node.next[0], node.next[1]
+            )
+        }
+        Action::Halt => return format!("{}return;\n", s),
+        Action::Stuck => return format!("{}{}\n", s, fail(&format!("{:?}", node.stuck()))),
+        Action::Operation(opcode) => rust_operation(opcode, node.pointer.reverse_mode, &at),
+    };
+    if !code.is_empty() {
+        s.push_str(&code);
+        s.push('\n');
+    }
+    writeln!(s, "{}", node.next[0]).expect("writing to a string");
+    s
+}
+
+/// A stack operation in Rust, the stacks hold enough elements. `at` is the context of errors.
+fn rust_operation(opcode: Opcode, reverse_mode: bool, at: &str) -> String {
+    let fail = |kind: &str| format!("m.fail({}, {})", kind, at);
+    let n = "let n = m.data.len();\n";
+    match opcode {
+        Opcode::Push => "m.data.push(0);".to_string(),
+        Opcode::Pop => format!(
+            "let top = m.data.pop().unwrap();\nif top != 0 {{\n    {};\n}}",
+            fail("format!(\"can not pop non-zero value {}\", top)")
+        ),
+        Opcode::Transfer1 => "let x = m.data.pop().unwrap();\nm.control.push(x);".to_string(),
+        Opcode::Transfer2 => "let x = m.control.pop().unwrap();\nm.data.push(x);".to_string(),
+        Opcode::Interchange => "let x = m.data.len() - 1;\nlet y = m.control.len() - 1;\n\
+             ::std::mem::swap(&mut m.data[x], &mut m.control[y]);"
+            .to_string(),
+        Opcode::Increment => format!("{}m.data[n - 1] = m.data[n - 1].wrapping_add(1);", n),
+        Opcode::Decrement => format!("{}m.data[n - 1] = m.data[n - 1].wrapping_sub(1);", n),
+        Opcode::Add => format!(
+            "{}m.data[n - 2] = m.data[n - 2].wrapping_add(m.data[n - 1]);",
+            n
+        ),
+        Opcode::Subtract => {
+            format!(
+                "{}m.data[n - 2] = m.data[n - 2].wrapping_sub(m.data[n - 1]);",
+                n
+            )
+        }
+        Opcode::Divide => format!(
+            "let x = m.data.pop().unwrap();\nif x == 0 {{\n    {};\n}}\n\
+             let y = m.data.pop().unwrap();\n\
+             m.data.push(y.wrapping_div(x));\nm.data.push(y.wrapping_rem(x));\nm.data.push(x);",
+            fail("\"division by zero\"")
+        ),
+        Opcode::Multiply => "let x = m.data.pop().unwrap();\nlet y = m.data.pop().unwrap();\n\
+             let z = m.data.pop().unwrap();\n\
+             m.data.push(z.wrapping_mul(x).wrapping_add(y));\nm.data.push(x);"
+            .to_string(),
+        Opcode::Not => format!("{}m.data[n - 1] = !m.data[n - 1];", n),
+        Opcode::And => format!("{}m.data[n - 3] ^= m.data[n - 1] & m.data[n - 2];", n),
+        Opcode::Or => format!("{}m.data[n - 3] ^= m.data[n - 1] | m.data[n - 2];", n),
+        Opcode::Xor => format!("{}m.data[n - 2] ^= m.data[n - 1];", n),
+        Opcode::Toggle => "*m.control.last_mut().unwrap() ^= 1;".to_string(),
+        Opcode::Equal | Opcode::Less | Opcode::Greater => {
+            let operator = match opcode {
+                Opcode::Equal => "==",
+                Opcode::Less => "<",
+                _ => ">",
+            };
+            format!(
+                "{}if m.data[n - 2] {} m.data[n - 1] {{\n    \
+                 *m.control.last_mut().unwrap() ^= 1;\n}}",
+                n, operator
+            )
+        }
+        Opcode::Swap1 => format!("{}m.data.swap(n - 1, n - 2);", n),
+        Opcode::Swap2 => format!("{}m.data.swap(n - 3, n - 2);", n),
+        Opcode::Swap3 => format!("{}m.data.swap(n - 3, n - 1);", n),
+        Opcode::Dig => format!("{}m.data[n - 3..].rotate_left(1);", n),
+        Opcode::Bury => format!("{}m.data[n - 3..].rotate_right(1);", n),
+        Opcode::Over => format!("{}let y = m.data[n - 2];\nm.data.push(y);", n),
+        Opcode::Under => format!(
+            "{}if m.data[n - 1] != m.data[n - 3] {{\n    let (top, third) = (m.data[n - 1], \
+             m.data[n - 3]);\n    {};\n}}\nm.data.pop();",
+            n,
+            fail("format!(\"can not under, top {} differs from third element {}\", top, third)")
+        ),
+        Opcode::Duplicate => format!("{}let x = m.data[n - 1];\nm.data.push(x);", n),
+        Opcode::Unduplicate => format!(
+            "{}if m.data[n - 1] != m.data[n - 2] {{\n    let (top, second) = (m.data[n - 1], \
+             m.data[n - 2]);\n    {};\n}}\nm.data.pop();",
+            n,
+            fail(
+                "format!(\"can not unduplicate, top {} differs from second element {}\", top, \
+                 second)"
+            )
+        ),
+        Opcode::Read if reverse_mode => format!("m.unread({});", at),
+        Opcode::Read => format!("m.read({});", at),
+        Opcode::Write if reverse_mode => format!("m.unwrite({});", at),
+        Opcode::Write => format!("m.write({});", at),
+        Opcode::RotateLeft => format!(
+            "{}m.data[n - 2] = m.data[n - 2].rotate_left(m.data[n - 1] as u32);",
+            n
+        ),
+        Opcode::RotateRight => format!(
+            "{}m.data[n - 2] = m.data[n - 2].rotate_right(m.data[n - 1] as u32);",
+            n
+        ),
+        Opcode::Nop
+        | Opcode::StringToggle
+        | Opcode::Reverse
+        | Opcode::Mirror1
+        | Opcode::Mirror2
+        | Opcode::Branch(_)
+        | Opcode::Interpret => unreachable!("not an operation: {:?}", opcode),
+    }
+}
+
+/// The source of a standalone C99 program that runs `program` like `befreak` does.
+///
+/// The runtime, `runtime/befreak.h`, is included in the source, so it compiles on its own. The
+/// stacks have a fixed capacity, see the runtime for this and how to replace stdio.
+pub fn c(program: &Program, cell: CellType) -> String {
+    let machine = StateMachine::new(program);
+    let mut s = String::new();
+    s.push_str("/* Translated from Befreak by `befreak compile`. */\n\n");
+    s.push_str("#include <inttypes.h>\n#include <stdint.h>\n\n");
+    let bits = match cell {
+        CellType::I32 => 32,
+        CellType::I64 => 64,
+    };
+    writeln!(
+        s,
+        "typedef int{0}_t befreak_cell;\ntypedef uint{0}_t befreak_ucell;\n\
+         #define BEFREAK_CELL_BITS {0}\n#define BEFREAK_CELL_FORMAT PRId{0}\n\
+         #define BEFREAK_RECORD_OUTPUT {1}\n",
+        bits,
+        machine.unwrites() as u8
+    )
+    .expect("writing to a string");
+    s.push_str(include_str!("../runtime/befreak.h"));
+    s.push_str("\nstatic void befreak_run(struct befreak_machine *m)\n{\n");
+    s.push_str("    unsigned long state = 0;\n    for (;;) {\n");
+    s.push_str("        befreak_tick(m);\n        switch (state) {\n");
+    for (i, node) in machine.nodes.iter().enumerate() {
+        writeln!(s, "        /* {} */", node.describe()).expect("writing to a string");
+        writeln!(s, "        case {}: {{", i).expect("writing to a string");
+        for line in c_state(node).lines() {
+            writeln!(s, "            {}", line).expect("writing to a string");
+        }
+        s.push_str("        }\n");
+    }
+    s.push_str("        }\n    }\n}\n");
+    s
+}
+
+/// `s` as a C string literal. Characters that are not ASCII are written as the octal escapes of
+/// their UTF-8 bytes, `?` is escaped so it can not start a trigraph.
+fn c_string(s: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' | '\\' | '?' => {
+                literal.push('\\');
+                literal.push(c);
+            }
+            ' '..='~' => literal.push(c),
+            _ => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    write!(literal, "\\{:03o}", byte).expect("writing to a string");
+                }
+            }
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// The statements of a state in C, ending with the assignment of the next state.
+fn c_state(node: &Node) -> String {
+    let at = c_string(&node.context());
+    let mut s = String::new();
+    if node.digit_end {
+        writeln!(
+            s,
+            "if (m->digits > 0) {{\n    befreak_digit_end(m, {});\n    befreak_tick(m);\n}}",
+            at
+        )
+        .expect("writing to a string");
+    }
+    let (data, control) = node.requirements();
+    if data > 0 || control > 0 {
+        writeln!(s, "befreak_need(m, {}, {}, {});", data, control, at)
+            .expect("writing to a string");
+    }
+    let code = match node.action {
+        Action::Move => String::new(),
+        Action::Digit(digit) => format!(
+            "befreak_digit(m, {}, {});",
+            digit, node.pointer.reverse_mode as u8
+        ),
+        Action::StringPush(c) => format!("befreak_push(&m->data, {}, {});", c as u32, at),
+        Action::StringPop(c) => format!(
+            "befreak_need(m, 1, 0, {0});\nif (BEFREAK_AT(m->data, 1) != {1}) {{\n    \
+             befreak_fail({0}, \"can not pop %s in string mode, top of the data stack is %\" \
+             BEFREAK_CELL_FORMAT,\n                 {2}, BEFREAK_AT(m->data, 1));\n}}\n\
+             befreak_pop(&m->data);",
+            at,
+            c as u32,
+            c_string(&format!("{:?}", c))
+        ),
+        Action::PushControl(value) => format!("befreak_push(&m->control, {}, {});", value, at),
+        Action::ToggleControl => "BEFREAK_AT(m->control, 1) ^= 1;".to_string(),
+        Action::Branch => {
+            return format!(
+                "{}state = befreak_pop(&m->control) == 0 ? {} : {};\nbreak;\n",
+                s, node.next[0], node.next[1]
+            )
+        }
+        Action::Halt => return format!("{}return;\n", s),
+        Action::Stuck => {
+            return format!("{}befreak_fail({}, {});\n", s, at, c_string(node.stuck()))
+        }
+        Action::Operation(opcode) => c_operation(opcode, node.pointer.reverse_mode, &at),
+    };
+    if !code.is_empty() {
+        s.push_str(&code);
+        s.push('\n');
+    }
+    writeln!(s, "state = {};\nbreak;", node.next[0]).expect("writing to a string");
+    s
+}
+
+/// A stack operation in C, the stacks hold enough elements. `at` is the context of errors as a
+/// string literal.
+fn c_operation(opcode: Opcode, reverse_mode: bool, at: &str) -> String {
+    let fail = |kind: &str| format!("befreak_fail({}, {});", at, kind);
+    let swap = |a: usize, b: usize| {
+        format!(
+            "befreak_cell x = BEFREAK_AT(m->data, {0});\n\
+             BEFREAK_AT(m->data, {0}) = BEFREAK_AT(m->data, {1});\nBEFREAK_AT(m->data, {1}) = x;",
+            a, b
+        )
+    };
+    let compare = |operator: &str| {
+        format!(
+            "if (BEFREAK_AT(m->data, 2) {} BEFREAK_AT(m->data, 1)) {{\n    \
+             BEFREAK_AT(m->control, 1) ^= 1;\n}}",
+            operator
+        )
+    };
+    match opcode {
+        Opcode::Push => format!("befreak_push(&m->data, 0, {});", at),
+        Opcode::Pop => format!(
+            "if (BEFREAK_AT(m->data, 1) != 0) {{\n    {}\n}}\nbefreak_pop(&m->data);",
+            fail("\"can not pop non-zero value %\" BEFREAK_CELL_FORMAT, BEFREAK_AT(m->data, 1)")
+        ),
+        Opcode::Transfer1 => format!("befreak_push(&m->control, befreak_pop(&m->data), {});", at),
+        Opcode::Transfer2 => format!("befreak_push(&m->data, befreak_pop(&m->control), {});", at),
+        Opcode::Interchange => "befreak_cell x = BEFREAK_AT(m->data, 1);\n\
+             BEFREAK_AT(m->data, 1) = BEFREAK_AT(m->control, 1);\n\
+             BEFREAK_AT(m->control, 1) = x;"
+            .to_string(),
+        Opcode::Increment => {
+            "BEFREAK_AT(m->data, 1) = BEFREAK_WRAP(BEFREAK_AT(m->data, 1), +, 1);".to_string()
+        }
+        Opcode::Decrement => {
+            "BEFREAK_AT(m->data, 1) = BEFREAK_WRAP(BEFREAK_AT(m->data, 1), -, 1);".to_string()
+        }
+        Opcode::Add => "BEFREAK_AT(m->data, 2) =\n    \
+             BEFREAK_WRAP(BEFREAK_AT(m->data, 2), +, BEFREAK_AT(m->data, 1));"
+            .to_string(),
+        Opcode::Subtract => "BEFREAK_AT(m->data, 2) =\n    \
+             BEFREAK_WRAP(BEFREAK_AT(m->data, 2), -, BEFREAK_AT(m->data, 1));"
+            .to_string(),
+        Opcode::Divide => format!(
+            "befreak_cell x = befreak_pop(&m->data);\nbefreak_cell y;\nif (x == 0) {{\n    {}\n}}\n\
+             y = befreak_pop(&m->data);\nbefreak_push(&m->data, befreak_div(y, x), {1});\n\
+             befreak_push(&m->data, befreak_rem(y, x), {1});\nbefreak_push(&m->data, x, {1});",
+            fail("\"division by zero\""),
+            at
+        ),
+        Opcode::Multiply => format!(
+            "befreak_cell x = befreak_pop(&m->data);\nbefreak_cell y = befreak_pop(&m->data);\n\
+             befreak_cell z = befreak_pop(&m->data);\n\
+             befreak_push(&m->data, BEFREAK_WRAP(BEFREAK_WRAP(z, *, x), +, y), {0});\n\
+             befreak_push(&m->data, x, {0});",
+            at
+        ),
+        Opcode::Not => "BEFREAK_AT(m->data, 1) = ~BEFREAK_AT(m->data, 1);".to_string(),
+        Opcode::And => {
+            "BEFREAK_AT(m->data, 3) ^= BEFREAK_AT(m->data, 1) & BEFREAK_AT(m->data, 2);".to_string()
+        }
+        Opcode::Or => {
+            "BEFREAK_AT(m->data, 3) ^= BEFREAK_AT(m->data, 1) | BEFREAK_AT(m->data, 2);".to_string()
+        }
+        Opcode::Xor => "BEFREAK_AT(m->data, 2) ^= BEFREAK_AT(m->data, 1);".to_string(),
+        Opcode::Toggle => "BEFREAK_AT(m->control, 1) ^= 1;".to_string(),
+        Opcode::Equal => compare("=="),
+        Opcode::Less => compare("<"),
+        Opcode::Greater => compare(">"),
+        Opcode::Swap1 => swap(1, 2),
+        Opcode::Swap2 => swap(3, 2),
+        Opcode::Swap3 => swap(3, 1),
+        Opcode::Dig => "befreak_cell x = BEFREAK_AT(m->data, 3);\n\
+             BEFREAK_AT(m->data, 3) = BEFREAK_AT(m->data, 2);\n\
+             BEFREAK_AT(m->data, 2) = BEFREAK_AT(m->data, 1);\nBEFREAK_AT(m->data, 1) = x;"
+            .to_string(),
+        Opcode::Bury => "befreak_cell x = BEFREAK_AT(m->data, 1);\n\
+             BEFREAK_AT(m->data, 1) = BEFREAK_AT(m->data, 2);\n\
+             BEFREAK_AT(m->data, 2) = BEFREAK_AT(m->data, 3);\nBEFREAK_AT(m->data, 3) = x;"
+            .to_string(),
+        Opcode::Over => format!("befreak_push(&m->data, BEFREAK_AT(m->data, 2), {});", at),
+        Opcode::Under => format!(
+            "if (BEFREAK_AT(m->data, 1) != BEFREAK_AT(m->data, 3)) {{\n    {}\n}}\n\
+             befreak_pop(&m->data);",
+            fail(
+                "\"can not under, top %\" BEFREAK_CELL_FORMAT\n                 \
+                 \" differs from third element %\" BEFREAK_CELL_FORMAT,\n                 \
+                 BEFREAK_AT(m->data, 1), BEFREAK_AT(m->data, 3)"
+            )
+        ),
+        Opcode::Duplicate => format!("befreak_push(&m->data, BEFREAK_AT(m->data, 1), {});", at),
+        Opcode::Unduplicate => format!(
+            "if (BEFREAK_AT(m->data, 1) != BEFREAK_AT(m->data, 2)) {{\n    {}\n}}\n\
+             befreak_pop(&m->data);",
+            fail(
+                "\"can not unduplicate, top %\" BEFREAK_CELL_FORMAT\n                 \
+                 \" differs from second element %\" BEFREAK_CELL_FORMAT,\n                 \
+                 BEFREAK_AT(m->data, 1), BEFREAK_AT(m->data, 2)"
+            )
+        ),
+        Opcode::Read if reverse_mode => format!("befreak_unread(m, {});", at),
+        Opcode::Read => format!("befreak_read(m, {});", at),
+        Opcode::Write if reverse_mode => format!("befreak_unwrite(m, {});", at),
+        Opcode::Write => format!("befreak_write(m, {});", at),
+        Opcode::RotateLeft => "BEFREAK_AT(m->data, 2) =\n    \
+             befreak_rotate_left(BEFREAK_AT(m->data, 2), BEFREAK_AT(m->data, 1));"
+            .to_string(),
+        Opcode::RotateRight => "BEFREAK_AT(m->data, 2) =\n    \
+             befreak_rotate_right(BEFREAK_AT(m->data, 2), BEFREAK_AT(m->data, 1));"
+            .to_string(),
+        Opcode::Nop
+        | Opcode::StringToggle
+        | Opcode::Reverse
+        | Opcode::Mirror1
+        | Opcode::Mirror2
+        | Opcode::Branch(_)
+        | Opcode::Interpret => unreachable!("not an operation: {:?}", opcode),
+    }
+}
@@ -0,0 +1,248 @@
+//! A full-screen terminal view that animates a program as it runs.
+
+use std::cmp;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use cell::Cell;
+use debugger::{Debugger, Stop};
+use io::MemoryIo;
+use state::State;
+
+/// The pause between two steps at each speed, the last speed runs as fast as possible.
+const DELAYS: &[u64] = &[1000, 500, 200, 100, 50, 20, 10, 0];
+/// The number of steps taken per frame at the fastest speed.
+const FAST_STEPS: usize = 1000;
+
+const KEYS: &str = "space: run/pause  s/→: step  b/←: step back  +/-: speed  r: restart  q: quit";
+
+struct Visualiser<C: Cell> {
+    debugger: Debugger<MemoryIo, C>,
+    initial_input: Vec<u8>,
+    running: bool,
+    speed: usize,
+    /// Why execution stopped last, shown until the next step.
+    message: String,
+}
+
+impl<C: Cell> Visualiser<C> {
+    fn new(state: State<MemoryIo, C>) -> Self {
+        Self {
+            initial_input: state.io.remaining_input().iter().cloned().collect(),
+            debugger: Debugger::new(state),
+            running: false,
+            speed: 3,
+            message: String::new(),
+        }
+    }
+
+    fn step(&mut self) {
+        match self.debugger.step(1) {
+            Stop::Done | Stop::Breakpoint(_) => self.message.clear(),
+            stop => self.stop(&stop),
+        }
+    }
+
+    fn step_back(&mut self) {
+        match self.debugger.back(1) {
+            Stop::Done | Stop::Breakpoint(_) => self.message.clear(),
+            stop => self.stop(&stop),
+        }
+    }
+
+    fn stop(&mut self, stop: &Stop<C>) {
+        self.running = false;
+        self.message = match stop {
+            Stop::Halted => "halted".to_owned(),
+            Stop::Stuck(e) => format!("error: {}", e),
+            Stop::StepBack(e) => e.to_string(),
+            Stop::Done | Stop::Breakpoint(_) => String::new(),
+        };
+    }
+
+    fn restart(&mut self) {
+        let program = self.debugger.state.program.to_string();
+        let program = program.parse().expect("a parsed program parses again");
+        self.debugger = Debugger::new(State::with_io(program, MemoryIo::new(&self.initial_input)));
+        self.running = false;
+        self.message.clear();
+    }
+
+    /// Handle a key press, returning false when the user quits.
+    fn key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char(' ') => self.running = !self.running,
+            KeyCode::Char('s') | KeyCode::Right => {
+                self.running = false;
+                self.step();
+            }
+            KeyCode::Char('b') | KeyCode::Left => {
+                self.running = false;
+                self.step_back();
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Up => {
+                self.speed = cmp::min(self.speed + 1, DELAYS.len() - 1)
+            }
+            KeyCode::Char('-') | KeyCode::Down => self.speed = self.speed.saturating_sub(1),
+            KeyCode::Char('r') => self.restart(),
+            _ => {}
+        }
+        true
+    }
+
+    /// The lines of the side panel, at most `rows` of them. Output that does not fit is cut off
+    /// at the top, so the latest output shows.
+    fn panel(&self, rows: usize) -> Vec<String> {
+        let state = &self.debugger.state;
+        let on_off = |b| if b { "on" } else { "off" };
+        let mut lines = vec![
+            format!("step:         {}", self.debugger.steps()),
+            format!(
+                "rule:         {}",
+                self.debugger.last_rule().map_or("", |rule| rule.name())
+            ),
+            format!(
+                "location:     line {}, col {}",
+                state.location.1 + 1,
+                state.location.0 + 1
+            ),
+            format!("direction:    {}", state.direction),
+            format!("reverse mode: {}", on_off(state.reverse_mode)),
+            format!("string mode:  {}", on_off(state.string_mode)),
+            format!("accumulator:  '{}'", state.multi_digit_accumulator),
+            format!("data:         {}", state.data_stack),
+            format!("control:      {}", state.control_stack),
+            String::new(),
+            format!(
+                "{}, {} ms per step",
+                if self.running { "running" } else { "paused" },
+                DELAYS[self.speed]
+            ),
+            self.message.clone(),
+            String::new(),
+            "output:".to_owned(),
+        ];
+        let output: Vec<&str> = state.io.output().lines().collect();
+        let shown = rows.saturating_sub(lines.len()).min(output.len());
+        lines.extend(
+            output[output.len() - shown..]
+                .iter()
+                .map(|&line| line.to_owned()),
+        );
+        lines.truncate(rows);
+        lines
+    }
+
+    fn render<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+        queue!(out, Clear(ClearType::All))?;
+
+        // the grid, with the instruction pointer in reverse video
+        let program = self.debugger.state.program.to_string();
+        let location = self.debugger.state.location;
+        for (y, line) in program.lines().take(height.saturating_sub(1)).enumerate() {
+            queue!(out, MoveTo(0, y as u16))?;
+            for (x, c) in line.chars().take(width).enumerate() {
+                if (x, y) == location {
+                    queue!(
+                        out,
+                        SetAttribute(Attribute::Reverse),
+                        Print(c),
+                        SetAttribute(Attribute::Reset)
+                    )?;
+                } else {
+                    queue!(out, Print(c))?;
+                }
+            }
+        }
+
+        // the panel to the right of the grid, stacks are cut off at the bottom so the top shows
+        let left = self.debugger.state.program.cols() + 3;
+        let room = width.saturating_sub(left);
+        for (y, line) in self.panel(height.saturating_sub(1)).iter().enumerate() {
+            let chars: Vec<char> = line.chars().collect();
+            let line: String = if chars.len() > room {
+                chars[chars.len() - room..].iter().collect()
+            } else {
+                line.clone()
+            };
+            queue!(out, MoveTo(left as u16, y as u16), Print(line))?;
+        }
+
+        let keys: String = KEYS.chars().take(width).collect();
+        queue!(out, MoveTo(0, height.saturating_sub(1) as u16), Print(keys))?;
+        out.flush()
+    }
+
+    fn event_loop<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let mut last_step = Instant::now();
+        loop {
+            self.render(out)?;
+
+            let delay = Duration::from_millis(DELAYS[self.speed]);
+            let timeout = if self.running {
+                delay.checked_sub(last_step.elapsed()).unwrap_or_default()
+            } else {
+                Duration::from_secs(60)
+            };
+            if event::poll(timeout)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !self.key(key.code) {
+                        return Ok(());
+                    }
+                }
+            }
+
+            if self.running && last_step.elapsed() >= delay {
+                let steps = if DELAYS[self.speed] == 0 {
+                    FAST_STEPS
+                } else {
+                    1
+                };
+                for _ in 0..steps {
+                    self.step();
+                    if !self.running {
+                        break;
+                    }
+                }
+                last_step = Instant::now();
+            }
+        }
+    }
+}
+
+/// Puts the terminal back the way it was when dropped, also when drawing fails or panics.
+struct Terminal;
+
+impl Terminal {
+    fn enter<W: Write>(out: &mut W) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let terminal = Terminal;
+        execute!(out, EnterAlternateScreen, Hide)?;
+        Ok(terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Show `state` full-screen until the user quits, stepping through it under keyboard control.
+///
+/// The program reads from and writes to the `MemoryIo`, its output is shown in the side panel.
+pub fn run<C: Cell>(state: State<MemoryIo, C>) -> io::Result<()> {
+    let mut out = io::stdout();
+    let _terminal = Terminal::enter(&mut out)?;
+    Visualiser::new(state).event_loop(&mut out)
+}
@@ -0,0 +1,210 @@
+//! Checks that a program really is reversible.
+//!
+//! The program is run forward until it halts. Then the direction is reversed and reverse mode is
+//! toggled, after which the program should retrace its steps back to the start symbol. After `k`
+//! backward steps the stacks, the digit accumulator and the modes should match those of the
+//! forward run `k` steps before it halted, with reverse mode toggled, and the pointer should be
+//! where the forward run came from, facing the other way. The states are compared in full, so the
+//! program is only reported reversible if every one of them is restored.
+//!
+//! Every state of the forward run is kept until the backward run has passed it, so a long run
+//! needs a lot of memory. `Limits` stop a forward run that takes too long.
+//!
+//! A number is only reversed as a whole: forward the digits are appended to the accumulator and
+//! xor-ed onto the stack at the end, backward the digits are prepended and xor-ed at the other
+//! end. The states in the middle of a number differ between the runs, so those are not compared.
+
+use std::error::Error;
+use std::fmt;
+use std::time::Instant;
+
+use cell::Cell;
+use direction::Direction;
+use instruction::{ExecutionError, InstructionExecutionStatus};
+use interpreter::{Limits, Outcome};
+use io::{Io, MemoryIo};
+use pointer::Pointer;
+use program::Program;
+use stack::Stack;
+use state::State;
+
+/// The result of a successful verification.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Verified {
+    /// The number of steps taken in each direction.
+    pub steps: usize,
+    /// Everything the program wrote during the forward run.
+    pub output: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VerificationError<C: Cell = i32> {
+    /// The input contains a byte `read` would not accept.
+    InvalidInput { byte: u8 },
+    /// The program got stuck running forward.
+    Forward(ExecutionError<C>),
+    /// The forward run was stopped by one of the `Limits` before it halted.
+    Stopped(Outcome),
+    /// The program got stuck after `step` backward steps.
+    Backward {
+        step: usize,
+        error: ExecutionError<C>,
+    },
+    /// After `step` backward steps the state no longer mirrors the forward run. `state` is the
+    /// `Display` representation of the backward state.
+    Diverged { step: usize, state: String },
+    /// The backward run did not end on the start symbol, facing west.
+    NotAtStart {
+        location: (usize, usize),
+        direction: Direction,
+    },
+}
+
+impl<C: Cell> fmt::Display for VerificationError<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerificationError::InvalidInput { byte } => {
+                write!(f, "input byte {:#04x} is not printable ASCII", byte)
+            }
+            VerificationError::Forward(e) => write!(f, "forward run failed: {}", e),
+            VerificationError::Stopped(outcome) => write!(f, "forward run stopped: {}", outcome),
+            VerificationError::Backward { step, error } => {
+                write!(f, "backward run failed after {} steps: {}", step, error)
+            }
+            VerificationError::Diverged { step, state } => write!(
+                f,
+                "backward run diverged from the forward run after {} steps, in state {}",
+                step, state
+            ),
+            VerificationError::NotAtStart {
+                location,
+                direction,
+            } => write!(
+                f,
+                "backward run ended at line {}, col {} going {} instead of on the start symbol",
+                location.1 + 1,
+                location.0 + 1,
+                direction
+            ),
+        }
+    }
+}
+
+impl<C: Cell> Error for VerificationError<C> {}
+
+/// Run `program` forward to halt and back again, reading from `input`.
+///
+/// The input is placed on the input stack before the program starts, so that the backward run
+/// can unread it and the input stack can be compared as well.
+pub fn verify<C: Cell>(program: Program, input: &[u8]) -> Result<Verified, VerificationError<C>> {
+    verify_with_limits(program, input, &Limits::default())
+}
+
+/// Like `verify`, stopping the forward run at the step limit or timeout of `limits`. Cycles are
+/// not detected.
+pub fn verify_with_limits<C: Cell>(
+    program: Program,
+    input: &[u8],
+    limits: &Limits,
+) -> Result<Verified, VerificationError<C>> {
+    let mut state: State<MemoryIo, C> = State::with_io(program, MemoryIo::default());
+    for &byte in input.iter().rev() {
+        if !byte.is_ascii() || byte.is_ascii_control() {
+            return Err(VerificationError::InvalidInput { byte });
+        }
+        state.input_stack.push(char::from(byte));
+    }
+
+    // run forward, remembering every state
+    let mut snapshots = vec![Snapshot::of(&state, false)];
+    let start = Instant::now();
+    loop {
+        let steps = snapshots.len() - 1;
+        if limits.max_steps.is_some_and(|max| steps >= max) {
+            return Err(VerificationError::Stopped(Outcome::StepLimit { steps }));
+        }
+        if limits
+            .timeout
+            .is_some_and(|timeout| start.elapsed() >= timeout)
+        {
+            return Err(VerificationError::Stopped(Outcome::Timeout { steps }));
+        }
+        match state.step() {
+            Ok(InstructionExecutionStatus::Successful(_)) => {
+                snapshots.push(Snapshot::of(&state, false))
+            }
+            Ok(InstructionExecutionStatus::Halt) => break,
+            Err(e) => return Err(VerificationError::Forward(e)),
+        }
+    }
+    let steps = snapshots.len() - 1;
+    let output = state.io.output().to_owned();
+
+    // turn around and run backward for the same number of steps
+    state.direction = state.direction.opposite();
+    state.reverse_mode = !state.reverse_mode;
+    state.location = state.next();
+    for step in 0..=steps {
+        if state.multi_digit_accumulator.is_empty()
+            && Snapshot::of(&state, true) != snapshots[steps - step]
+        {
+            return Err(VerificationError::Diverged {
+                step,
+                state: state.to_string(),
+            });
+        }
+        if step == steps {
+            break;
+        }
+        if let Err(error) = state.step() {
+            return Err(VerificationError::Backward { step, error });
+        }
+    }
+
+    if !state.instr().is_halt() || state.direction != Direction::West {
+        return Err(VerificationError::NotAtStart {
+            location: state.location,
+            direction: state.direction,
+        });
+    }
+
+    Ok(Verified { steps, output })
+}
+
+/// Everything a backward run should restore.
+#[derive(PartialEq)]
+struct Snapshot<C: Cell> {
+    location: (usize, usize),
+    direction: Direction,
+    data_stack: Stack<C>,
+    control_stack: Stack<C>,
+    input_stack: Stack<char>,
+    output_stack: Stack<char>,
+    multi_digit_accumulator: String,
+    string_mode: bool,
+    reverse_mode: bool,
+}
+
+impl<C: Cell> Snapshot<C> {
+    /// Take a snapshot of `state`, with reverse mode toggled if `backward`. A forward state is
+    /// taken from behind: a backward run that undid its instruction has moved on to the cell it
+    /// came from, facing the other way.
+    fn of<I: Io>(state: &State<I, C>, backward: bool) -> Self {
+        let mut pointer = Pointer::of(state);
+        if !backward {
+            pointer.direction = pointer.direction.opposite();
+            pointer = pointer.advance(&state.program);
+        }
+        Snapshot {
+            location: pointer.location,
+            direction: pointer.direction,
+            data_stack: state.data_stack.clone(),
+            control_stack: state.control_stack.clone(),
+            input_stack: state.input_stack.clone(),
+            output_stack: state.output_stack.clone(),
+            multi_digit_accumulator: state.multi_digit_accumulator.clone(),
+            string_mode: state.string_mode,
+            reverse_mode: state.reverse_mode ^ backward,
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Arbitrary-precision cells, these tests only run with `--features bignum`.
+
+#![cfg(feature = "bignum")]
+
+extern crate befreak;
+extern crate num_bigint;
+
+use befreak::instruction::ExecutionErrorKind;
+use befreak::interpreter;
+use befreak::verify::verify;
+use befreak::{MemoryIo, Program, State};
+use num_bigint::BigInt;
+
+fn state(program: &str) -> State<MemoryIo, BigInt> {
+    let program: Program = program.parse().unwrap();
+    State::with_io(program, MemoryIo::default())
+}
+
+/// The data stack from the bottom up.
+fn data(s: &State<MemoryIo, BigInt>) -> Vec<BigInt> {
+    (0..s.data_stack.len())
+        .map(|i| s.data_stack.get(i).unwrap())
+        .collect()
+}
+
+fn big(digits: &str) -> BigInt {
+    digits.parse().unwrap()
+}
+
+#[test]
+fn arithmetic_goes_past_i64() {
+    // every `(s*` multiplies the second element by the top one, 10^6
+    let mut s = state("@(1(1000000(s*(s*(s*(s*");
+    interpreter::run(&mut s, &mut ()).unwrap();
+    assert_eq!(
+        data(&s),
+        vec![big("1000000000000000000000000"), big("1000000")]
+    );
+}
+
+#[test]
+fn divide_and_multiply_undo_each_other() {
+    let source = "@(123456789012345678901234567890(10000000000%*";
+    let mut s = state(source);
+    // up to and including `%`, after the digit_end of the divisor
+    while s.instr().to_string() != "%" || !s.multi_digit_accumulator.is_empty() {
+        s.step().unwrap();
+    }
+    s.step().unwrap();
+    assert_eq!(
+        data(&s),
+        vec![
+            big("12345678901234567890"),
+            big("1234567890"),
+            big("10000000000")
+        ]
+    );
+
+    interpreter::run(&mut s, &mut ()).unwrap();
+    assert_eq!(
+        data(&s),
+        vec![big("123456789012345678901234567890"), big("10000000000")]
+    );
+
+    // and in reverse mode, where `%` multiplies and `*` divides
+    verify::<BigInt>(source.parse().unwrap(), b"").unwrap();
+}
+
+#[test]
+fn rotation_is_unbounded() {
+    for source in &["@(1(1{", "@(1(1}"] {
+        let mut s = state(source);
+        let error = interpreter::run(&mut s, &mut ()).unwrap_err();
+        assert_eq!(
+            error.kind,
+            ExecutionErrorKind::UnboundedRotation,
+            "{}",
+            source
+        );
+    }
+}
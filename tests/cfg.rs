@@ -0,0 +1,90 @@
+extern crate befreak;
+
+use befreak::cfg::{ControlFlowGraph, Edge, Effect, End};
+use befreak::{Direction, Program};
+
+fn cfg(program: &str) -> ControlFlowGraph {
+    let program: Program = program.parse().unwrap();
+    ControlFlowGraph::new(&program)
+}
+
+fn text(cfg: &ControlFlowGraph, block: usize) -> String {
+    cfg.blocks[block]
+        .instructions
+        .iter()
+        .map(|i| i.to_string())
+        .collect()
+}
+
+#[test]
+fn straight_line() {
+    let cfg = cfg("@\"iH\"ww");
+    assert_eq!(cfg.blocks.len(), 1);
+    assert_eq!(text(&cfg, 0), "\"iH\"ww@");
+    assert_eq!(cfg.blocks[0].end, End::Halt);
+    assert!(cfg.edges.is_empty());
+}
+
+#[test]
+fn branches() {
+    // `<` entered from the opposite side pops to decide, going around the column it is entered
+    // from the side and pushes the turn
+    let cfg = cfg("  w\n@< \n  w");
+    assert_eq!(cfg.blocks.len(), 4);
+    assert_eq!(text(&cfg, 0), "<");
+    assert_eq!(text(&cfg, 1), "  <");
+    assert_eq!(text(&cfg, 2), "  <");
+    assert_eq!(text(&cfg, 3), "@");
+    assert_eq!(cfg.blocks[3].end, End::Halt);
+    assert_eq!(
+        cfg.edges,
+        vec![
+            Edge {
+                from: 0,
+                to: 1,
+                direction: Direction::North,
+                effect: Effect::PopZero,
+            },
+            Edge {
+                from: 0,
+                to: 2,
+                direction: Direction::South,
+                effect: Effect::PopNonZero,
+            },
+            Edge {
+                from: 1,
+                to: 3,
+                direction: Direction::West,
+                effect: Effect::Push(0),
+            },
+            Edge {
+                from: 2,
+                to: 3,
+                direction: Direction::West,
+                effect: Effect::Push(1),
+            },
+        ]
+    );
+}
+
+#[test]
+fn reverse_mode() {
+    // `>` entered from behind toggles, then the start symbol is stuck in reverse mode
+    let cfg = cfg("@(>");
+    assert_eq!(cfg.blocks.len(), 2);
+    assert_eq!(text(&cfg, 0), "(>");
+    assert_eq!(text(&cfg, 1), "(@");
+    assert!(cfg.blocks[1].pointers[0].reverse_mode);
+    assert_eq!(cfg.blocks[1].end, End::Stuck);
+    assert_eq!(cfg.edges[0].effect, Effect::Toggle);
+}
+
+#[test]
+fn dot() {
+    let dot = cfg("@\"\\\"").dot();
+    assert_eq!(
+        dot,
+        "digraph cfg {\n    node [shape=box, fontname=monospace];\n    start [shape=point];\n    \
+         start -> b0;\n    b0 [label=\"line 1, col 2 →\\n\\\"\\\\\\\"@\", peripheries=2];\n}\n"
+    );
+}
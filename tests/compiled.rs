@@ -0,0 +1,236 @@
+//! Differential tests: the compiled form must take exactly the steps of the interpreter.
+
+extern crate befreak;
+
+use befreak::compiled::{CompiledProgram, Opcode};
+use befreak::interpreter::{self, Limits, Outcome};
+use befreak::pointer::Pointer;
+use befreak::superinstruction::Fused;
+use befreak::{Cell, Direction, MemoryIo, Program, State};
+
+/// A small deterministic random number generator, a linear congruential generator.
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % n
+    }
+}
+
+fn assert_same<C: Cell>(a: &State<MemoryIo, C>, b: &State<MemoryIo, C>, context: &str) {
+    assert_eq!(a.location, b.location, "{}", context);
+    assert_eq!(a.direction, b.direction, "{}", context);
+    assert_eq!(a.reverse_mode, b.reverse_mode, "{}", context);
+    assert_eq!(a.string_mode, b.string_mode, "{}", context);
+    assert_eq!(
+        a.multi_digit_accumulator, b.multi_digit_accumulator,
+        "{}",
+        context
+    );
+    assert_eq!(a.data_stack, b.data_stack, "{}", context);
+    assert_eq!(a.control_stack, b.control_stack, "{}", context);
+    assert_eq!(a.input_stack, b.input_stack, "{}", context);
+    assert_eq!(a.output_stack, b.output_stack, "{}", context);
+    assert_eq!(a.io.output(), b.io.output(), "{}", context);
+}
+
+/// Step `interpreted` and `compiled` side by side for at most `steps` steps, checking that every
+/// step has the same result and leaves the same state. Returns the number of steps taken.
+fn compare<C: Cell>(
+    mut interpreted: State<MemoryIo, C>,
+    mut compiled: State<MemoryIo, C>,
+    steps: usize,
+    context: &str,
+) -> usize {
+    let program = CompiledProgram::new(&interpreted.program);
+    for step in 0..steps {
+        let context = format!("{} at step {}", context, step);
+        let expected = interpreted.step();
+        let result = program.step(&mut compiled);
+        assert_eq!(result, expected, "{}", context);
+        assert_same(&interpreted, &compiled, &context);
+        if !matches!(
+            result,
+            Ok(befreak::InstructionExecutionStatus::Successful(_))
+        ) {
+            return step;
+        }
+    }
+    steps
+}
+
+fn state<C: Cell>(program: &str) -> State<MemoryIo, C> {
+    let program: Program = program.parse().expect("valid program");
+    State::with_io(program, MemoryIo::default())
+}
+
+#[test]
+fn examples() {
+    let examples = [
+        ("hello", include_str!("../programs/hello")),
+        ("hello2", include_str!("../programs/hello2")),
+        ("hello3", include_str!("../programs/hello3")),
+        ("primes", include_str!("../programs/primes")),
+        ("primes2", include_str!("../programs/primes2")),
+        ("primes3", include_str!("../programs/primes3")),
+    ];
+    for &(name, source) in &examples {
+        compare::<i32>(state(source), state(source), 200_000, name);
+        compare::<i64>(state(source), state(source), 20_000, name);
+    }
+}
+
+/// The characters random programs are made of.
+const ALPHABET: &[u8] = b"     ()[]$rw'`+-%*~&|#{}!=lgsdbfcou:;\"?\\/<>v^0123456789@x";
+
+#[test]
+fn random_programs() {
+    let mut random = Random(2019);
+    for n in 0..3000 {
+        let (rows, cols) = (1 + random.below(5), 1 + random.below(8));
+        let lines: Vec<String> = (0..rows)
+            .map(|_| {
+                (0..cols)
+                    .map(|_| ALPHABET[random.below(ALPHABET.len())] as char)
+                    .collect()
+            })
+            .collect();
+        let source = lines.join("\n");
+        let mut interpreted = state::<i32>(&source);
+        let mut compiled = state::<i32>(&source);
+        for _ in 0..random.below(5) {
+            let x = random.below(7) as i32 - 3;
+            interpreted.data_stack.push(x);
+            compiled.data_stack.push(x);
+        }
+        for _ in 0..random.below(4) {
+            let c = random.below(2) as i32;
+            interpreted.control_stack.push(c);
+            compiled.control_stack.push(c);
+        }
+        let context = format!("program {} {:?}", n, source);
+        compare(interpreted, compiled, 300, &context);
+    }
+}
+
+/// Run `interpreted` with the interpreter and `compiled` with superinstructions for at most
+/// `max_steps` steps, checking that both end the same way in the same state.
+fn compare_runs<C: Cell>(
+    mut interpreted: State<MemoryIo, C>,
+    mut compiled: State<MemoryIo, C>,
+    max_steps: usize,
+    context: &str,
+) {
+    let limits = Limits {
+        max_steps: Some(max_steps),
+        ..Limits::default()
+    };
+    let expected = interpreter::run_with_limits(&mut interpreted, &mut (), &limits);
+    let result =
+        CompiledProgram::new(&compiled.program).run_with_max_steps(&mut compiled, max_steps);
+    assert_eq!(result, expected, "{}", context);
+    assert_same(&interpreted, &compiled, context);
+}
+
+#[test]
+fn superinstruction_examples() {
+    let examples = [
+        ("hello", include_str!("../programs/hello")),
+        ("hello2", include_str!("../programs/hello2")),
+        ("hello3", include_str!("../programs/hello3")),
+        ("primes", include_str!("../programs/primes")),
+        ("primes2", include_str!("../programs/primes2")),
+        ("primes3", include_str!("../programs/primes3")),
+    ];
+    for &(name, source) in &examples {
+        // stopping in the middle of traces too
+        for &max_steps in &[1, 2, 3, 10, 99, 1000, 12345, 200_000] {
+            let context = format!("{} for {} steps", name, max_steps);
+            compare_runs::<i32>(state(source), state(source), max_steps, &context);
+        }
+        compare_runs::<i64>(state(source), state(source), 20_000, name);
+    }
+}
+
+#[test]
+fn superinstruction_random_programs() {
+    let mut random = Random(2024);
+    for n in 0..3000 {
+        let (rows, cols) = (1 + random.below(5), 1 + random.below(8));
+        let lines: Vec<String> = (0..rows)
+            .map(|_| {
+                (0..cols)
+                    .map(|_| ALPHABET[random.below(ALPHABET.len())] as char)
+                    .collect()
+            })
+            .collect();
+        let source = lines.join("\n");
+        let mut interpreted = state::<i32>(&source);
+        let mut compiled = state::<i32>(&source);
+        for _ in 0..random.below(5) {
+            let x = random.below(7) as i32 - 3;
+            interpreted.data_stack.push(x);
+            compiled.data_stack.push(x);
+        }
+        for _ in 0..random.below(4) {
+            let c = random.below(2) as i32;
+            interpreted.control_stack.push(c);
+            compiled.control_stack.push(c);
+        }
+        let max_steps = random.below(300);
+        let context = format!("program {} {:?} for {} steps", n, source, max_steps);
+        compare_runs(interpreted, compiled, max_steps, &context);
+    }
+}
+
+#[test]
+fn fused_operations() {
+    let source = "@(''`'12 \"ab\"\\\n             w";
+    let compiled = state::<i32>(source);
+    let program = CompiledProgram::new(&compiled.program);
+    let start = Pointer {
+        location: (1, 0),
+        direction: Direction::East,
+        reverse_mode: false,
+        string_mode: false,
+    };
+    let trace = program.trace(start).expect("a trace at the start");
+    let fused: Vec<&Fused> = trace.operations.iter().map(|o| &o.fused).collect();
+    assert_eq!(
+        fused,
+        [
+            &Fused::Single(Opcode::Push),
+            &Fused::Add(2),
+            &Fused::Xor(12),
+            &Fused::PushString(vec!['a', 'b']),
+        ]
+    );
+    // `(`, four increments, two digits and digit_end, a space, the string and the mirror
+    assert_eq!(trace.steps, 1 + 4 + 3 + 1 + 4 + 1);
+    // the write is not fused
+    let end = trace.operations.last().unwrap().end.unwrap();
+    assert_eq!((end.location, end.direction), ((13, 1), Direction::South));
+}
+
+#[test]
+fn run() {
+    let source = include_str!("../programs/hello");
+    let mut interpreted = state::<i32>(source);
+    let steps =
+        interpreter::run_with_limits(&mut interpreted, &mut (), &Limits::default()).unwrap();
+    let mut compiled = state::<i32>(source);
+    let compiled_steps = CompiledProgram::new(&compiled.program)
+        .run(&mut compiled)
+        .unwrap();
+    assert_eq!(
+        steps,
+        Outcome::Halted {
+            steps: compiled_steps
+        }
+    );
+    assert_same(&interpreted, &compiled, "hello");
+}
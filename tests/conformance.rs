@@ -0,0 +1,440 @@
+//! Runs every instruction of the Befreak specification forward and then backward, checking both
+//! the effect of the instruction and that its inverse undoes it.
+
+extern crate befreak;
+
+use befreak::{Direction, Instruction, InstructionExecutionStatus, MemoryIo, Program, Rule, State};
+
+/// Create a state for `program` with the given data and control stacks, listed bottom to top.
+fn state(program: &str, input: &str, data: &[i32], control: &[i32]) -> State<MemoryIo> {
+    let program: Program = program.parse().expect("valid program");
+    let mut state = State::with_io(program, MemoryIo::new(input.as_bytes()));
+    for &x in data {
+        state.data_stack.push(x);
+    }
+    for &c in control {
+        state.control_stack.push(c);
+    }
+    state
+}
+
+/// Take `steps` steps of `program` and compare the stacks with `expected_data` and
+/// `expected_control`. Then turn around, toggle reverse mode and take the same number of steps
+/// back, which should end on the start symbol with the original stacks.
+fn check(
+    program: &str,
+    data: &[i32],
+    control: &[i32],
+    steps: usize,
+    expected_data: &str,
+    expected_control: &str,
+) -> State<MemoryIo> {
+    check_io(
+        program,
+        "",
+        data,
+        control,
+        steps,
+        expected_data,
+        expected_control,
+    )
+}
+
+fn check_io(
+    program: &str,
+    input: &str,
+    data: &[i32],
+    control: &[i32],
+    steps: usize,
+    expected_data: &str,
+    expected_control: &str,
+) -> State<MemoryIo> {
+    let mut s = state(program, input, data, control);
+    let (original_data, original_control) = (s.data_stack.to_string(), s.control_stack.to_string());
+
+    for _ in 0..steps {
+        match s.step() {
+            Ok(InstructionExecutionStatus::Successful(_)) => {}
+            Ok(InstructionExecutionStatus::Halt) => panic!("{:?} halted", program),
+            Err(e) => panic!("{:?} got stuck: {}", program, e),
+        }
+    }
+    assert_eq!(s.data_stack.to_string(), expected_data, "{:?}", program);
+    assert_eq!(
+        s.control_stack.to_string(),
+        expected_control,
+        "{:?}",
+        program
+    );
+    let after = s.to_string();
+
+    // run the same steps backwards
+    s.direction = s.direction.opposite();
+    s.reverse_mode = !s.reverse_mode;
+    s.location = s.next();
+    for _ in 0..steps {
+        if let Err(e) = s.step() {
+            panic!("{:?} got stuck backwards from {}: {}", program, after, e);
+        }
+    }
+    assert_eq!(
+        s.data_stack.to_string(),
+        original_data,
+        "{:?} backwards",
+        program
+    );
+    assert_eq!(
+        s.control_stack.to_string(),
+        original_control,
+        "{:?} backwards",
+        program
+    );
+    assert!(s.output_stack.is_empty() && s.input_stack.len() <= input.len());
+    assert!(s.multi_digit_accumulator.is_empty());
+    assert!(!s.string_mode);
+    // back on the start symbol, facing the other way
+    assert!(s.instr().is_halt(), "{:?} backwards", program);
+    assert_eq!(s.direction, Direction::West);
+    assert!(s.reverse_mode);
+
+    s
+}
+
+#[test]
+fn inverses() {
+    let pairs = [
+        ('(', ')'),
+        ('[', ']'),
+        ('\'', '`'),
+        ('+', '-'),
+        ('%', '*'),
+        ('{', '}'),
+        ('d', 'b'),
+        ('o', 'u'),
+        (':', ';'),
+    ];
+    let self_inverse = " $wr~&|#!=lgsfc\"?@\\/><v^0123456789";
+
+    for &(a, b) in &pairs {
+        let (a, b) = (
+            Instruction::from_char(a).unwrap(),
+            Instruction::from_char(b).unwrap(),
+        );
+        assert_eq!(a.inv(), b);
+        assert_eq!(b.inv(), a);
+    }
+    for c in self_inverse.chars() {
+        let i = Instruction::from_char(c).unwrap();
+        assert_eq!(i.inv(), i, "{:?}", c);
+    }
+}
+
+#[test]
+fn nop() {
+    check("@ ", &[], &[], 1, "ε", "ε");
+}
+
+#[test]
+fn push_pop() {
+    check("@(", &[], &[], 1, "0", "ε");
+    check("@)", &[0], &[], 1, "ε", "ε");
+}
+
+#[test]
+fn transfer_and_interchange() {
+    check("@[", &[7], &[], 1, "ε", "7");
+    check("@]", &[], &[7], 1, "7", "ε");
+    check("@$", &[1], &[2], 1, "2", "1");
+}
+
+#[test]
+fn write() {
+    let s = check("@w", &[65], &[], 1, "ε", "ε");
+    assert_eq!(s.io.output(), "A");
+}
+
+#[test]
+fn read() {
+    let s = check_io("@r", "A", &[], &[], 1, "65", "ε");
+    // the character is put back on the input stack, not in the io backend
+    assert_eq!(s.input_stack.to_string(), "A");
+}
+
+#[test]
+fn arithmetic() {
+    check("@'", &[4], &[], 1, "5", "ε");
+    check("@`", &[4], &[], 1, "3", "ε");
+    check("@`", &[0], &[], 1, "-1", "ε");
+    check("@+", &[5, 3], &[], 1, "8:3", "ε");
+    check("@-", &[5, 3], &[], 1, "2:3", "ε");
+    check("@-", &[3, 5], &[], 1, "-2:5", "ε");
+    check("@%", &[17, 5], &[], 1, "3:2:5", "ε");
+    check("@%", &[-17, 5], &[], 1, "-3:-2:5", "ε");
+    check("@*", &[3, 2, 5], &[], 1, "17:5", "ε");
+}
+
+#[test]
+fn wrapping_arithmetic() {
+    check("@'", &[i32::MAX], &[], 1, &i32::MIN.to_string(), "ε");
+    check(
+        "@+",
+        &[i32::MAX, 1],
+        &[],
+        1,
+        &format!("{}:1", i32::MIN),
+        "ε",
+    );
+    check(
+        "@%",
+        &[i32::MIN, -1],
+        &[],
+        1,
+        &format!("{}:0:-1", i32::MIN),
+        "ε",
+    );
+}
+
+#[test]
+fn bitwise() {
+    check("@~", &[5], &[], 1, "-6", "ε");
+    check("@&", &[1, 6, 3], &[], 1, "3:6:3", "ε");
+    check("@|", &[1, 6, 3], &[], 1, "6:6:3", "ε");
+    check("@#", &[6, 3], &[], 1, "5:3", "ε");
+    check("@{", &[1, 3], &[], 1, "8:3", "ε");
+    check("@}", &[8, 3], &[], 1, "1:3", "ε");
+    check("@{", &[-1, 7], &[], 1, "-1:7", "ε");
+}
+
+#[test]
+fn control() {
+    check("@!", &[], &[0], 1, "ε", "1");
+    check("@=", &[4, 4], &[0], 1, "4:4", "1");
+    check("@=", &[4, 5], &[0], 1, "4:5", "0");
+    check("@l", &[4, 5], &[0], 1, "4:5", "1");
+    check("@l", &[5, 4], &[0], 1, "5:4", "0");
+    check("@g", &[5, 4], &[0], 1, "5:4", "1");
+    check("@g", &[4, 5], &[0], 1, "4:5", "0");
+}
+
+#[test]
+fn stack_manipulation() {
+    check("@s", &[1, 2], &[], 1, "2:1", "ε");
+    check("@d", &[1, 2, 3], &[], 1, "2:3:1", "ε");
+    check("@b", &[1, 2, 3], &[], 1, "3:1:2", "ε");
+    check("@f", &[1, 2, 3], &[], 1, "3:2:1", "ε");
+    check("@c", &[1, 2, 3], &[], 1, "2:1:3", "ε");
+    check("@o", &[1, 2], &[], 1, "1:2:1", "ε");
+    check("@u", &[1, 2, 1], &[], 1, "1:2", "ε");
+    check("@:", &[7], &[], 1, "7:7", "ε");
+    check("@;", &[7, 7], &[], 1, "7", "ε");
+}
+
+#[test]
+fn digits() {
+    // two digits, digit_end on the space and then the space itself
+    check("@12 ", &[5], &[], 4, "9", "ε");
+}
+
+#[test]
+fn string_mode() {
+    check("@\"A(\"", &[], &[], 4, "65:40", "ε");
+}
+
+#[test]
+fn reverse_mode() {
+    let mut s = state("@?", "", &[], &[]);
+    s.step().unwrap();
+    assert!(s.reverse_mode);
+    // `(` now acts as `)`
+    let mut s = state("@?( ", "", &[0], &[]);
+    s.step().unwrap();
+    s.step().unwrap();
+    assert_eq!(s.data_stack.to_string(), "ε");
+    check("@?( ", &[0], &[], 3, "ε", "ε");
+}
+
+#[test]
+fn halt() {
+    let mut s = state("@", "", &[], &[]);
+    match s.step() {
+        Ok(InstructionExecutionStatus::Halt) => {}
+        _ => panic!("should halt"),
+    }
+    s.reverse_mode = true;
+    assert!(s.step().is_err());
+}
+
+#[test]
+fn mirrors() {
+    let s = check("   \n@\\ \n   ", &[], &[], 1, "ε", "ε");
+    assert_eq!(s.direction, Direction::West);
+    let mut s = state("   \n@\\ \n   ", "", &[], &[]);
+    s.step().unwrap();
+    assert_eq!(s.direction, Direction::South);
+    let mut s = state("   \n@/ \n   ", "", &[], &[]);
+    s.step().unwrap();
+    assert_eq!(s.direction, Direction::North);
+    check("   \n@/ \n   ", &[], &[], 1, "ε", "ε");
+}
+
+#[test]
+fn branch_from_the_side() {
+    // enter `>` going south, a left turn
+    check("@\\ \n > \n   ", &[], &[], 2, "ε", "0");
+    // enter `>` going north, a right turn
+    check(" > \n@/ \n   ", &[], &[], 2, "ε", "1");
+    check("@\\ \n v \n   ", &[], &[], 1, "ε", "ε");
+    check("@\\ \n < \n   ", &[], &[], 2, "ε", "1");
+    check(" ^ \n@/ \n   ", &[], &[], 1, "ε", "ε");
+}
+
+#[test]
+fn branch_from_the_opposite_side() {
+    let mut s = state("   \n@< \n   ", "", &[], &[0]);
+    s.step().unwrap();
+    assert_eq!(s.direction, Direction::North);
+    let mut s = state("   \n@< \n   ", "", &[], &[1]);
+    s.step().unwrap();
+    assert_eq!(s.direction, Direction::South);
+    check("   \n@< \n   ", &[], &[0], 1, "ε", "ε");
+    check("   \n@< \n   ", &[], &[1], 1, "ε", "ε");
+}
+
+#[test]
+fn branch_from_the_same_side() {
+    let mut s = state("   \n@> \n   ", "", &[], &[0]);
+    s.step().unwrap();
+    assert_eq!(s.direction, Direction::West);
+    assert!(s.reverse_mode);
+    assert_eq!(s.control_stack.to_string(), "1");
+    check("   \n@> \n   ", &[], &[0], 1, "ε", "1");
+}
+
+/// The rules applied by `steps` steps of `program`, forwards and then backwards.
+fn rules(program: &str, data: &[i32], control: &[i32], steps: usize) -> (Vec<Rule>, Vec<Rule>) {
+    let mut s = state(program, "", data, control);
+    let forward = (0..steps)
+        .map(|_| match s.step() {
+            Ok(InstructionExecutionStatus::Successful(rule)) => rule,
+            result => panic!("{:?} did not apply a rule: {:?}", program, result),
+        })
+        .collect();
+    let backward = (0..steps).map(|_| s.step_back().unwrap()).collect();
+    (forward, backward)
+}
+
+#[test]
+fn rules_applied() {
+    assert_eq!(
+        rules("@\"iH\"ww", &[], &[], 6),
+        (
+            vec![
+                Rule::StringToggle,
+                Rule::StringPush,
+                Rule::StringPush,
+                Rule::StringToggle,
+                Rule::Write,
+                Rule::Write,
+            ],
+            vec![
+                Rule::Unwrite,
+                Rule::Unwrite,
+                Rule::StringToggle,
+                Rule::StringPop,
+                Rule::StringPop,
+                Rule::StringToggle,
+            ],
+        )
+    );
+    assert_eq!(
+        rules("@=l!g", &[1, 2], &[0], 4).0,
+        vec![
+            Rule::EqualFalse,
+            Rule::LessTrue,
+            Rule::Toggle,
+            Rule::GreaterFalse,
+        ]
+    );
+    assert_eq!(
+        rules("@[$]", &[1, 5], &[2], 3),
+        (
+            vec![Rule::Transfer1, Rule::Interchange, Rule::Transfer2],
+            vec![Rule::Transfer1, Rule::Interchange, Rule::Transfer2],
+        )
+    );
+}
+
+#[test]
+fn rule_names() {
+    for (i, a) in Rule::ALL.iter().enumerate() {
+        assert!(!a.description().is_empty());
+        assert!(a.latex_name().starts_with("\\textrm{"));
+        for b in &Rule::ALL[i + 1..] {
+            assert_ne!(a.name(), b.name());
+            assert_ne!(a.latex_name(), b.latex_name());
+        }
+    }
+    assert_eq!(Rule::Branch1.to_string(), "branch_1");
+    assert_eq!(
+        Rule::RotateRight.latex_name(),
+        "\\textrm{rotate}_{\\,\\textrm{right}}"
+    );
+}
+
+#[test]
+fn pointer_wraps_around() {
+    let mut s = state("@ \n  ", "", &[], &[]);
+    s.location = (0, 0);
+    s.direction = Direction::North;
+    assert_eq!(s.next(), (0, 1));
+    s.direction = Direction::West;
+    assert_eq!(s.next(), (1, 0));
+}
+
+#[test]
+fn unwrite_moves_on() {
+    let mut s = state("@w w ", "", &[65], &[]);
+    s.step().unwrap();
+    s.step().unwrap();
+    s.reverse_mode = true;
+    s.step().unwrap();
+    assert_eq!(s.location, (4, 0));
+    assert_eq!(s.data_stack.to_string(), "65");
+    assert_eq!(s.io.output(), "A");
+}
+
+#[test]
+fn strings_are_not_inverted() {
+    // in reverse mode `(` is `)`, but inside a string it is still the character 40
+    let mut s = state("@?\"(\"", "", &[40], &[]);
+    s.step().unwrap();
+    s.step().unwrap();
+    assert_eq!(s.instr().to_string(), "(");
+    s.step().unwrap();
+    assert_eq!(s.data_stack.to_string(), "ε");
+}
+
+#[test]
+fn less_rule_names() {
+    assert_eq!(
+        Rule::LessTrue.latex_name(),
+        "\\textrm{less}_{\\,\\textrm{true}}"
+    );
+    assert_eq!(
+        Rule::LessFalse.latex_name(),
+        "\\textrm{less}_{\\,\\textrm{false}}"
+    );
+}
+
+#[test]
+fn start_symbol_in_a_string() {
+    // the first start symbol is the start, the second one is only a character of the string
+    let program: Program = "@\"@\"w".parse().unwrap();
+    assert_eq!(program.start_symbols(), vec![(0, 0), (2, 0)]);
+    assert_eq!(program.lookup(), (1, 0));
+    let mut s = state("@\"@\"w", "", &[], &[]);
+    while let Ok(InstructionExecutionStatus::Successful(_)) = s.step() {}
+    assert!(s.instr().is_halt());
+    assert_eq!(s.location, (0, 0));
+    assert_eq!(s.io.output(), "@");
+}
@@ -0,0 +1,236 @@
+extern crate befreak;
+
+use befreak::condition::Condition;
+use befreak::debugger::{Breakpoint, Command, Debugger, Stop};
+use befreak::{MemoryIo, Program, State, StepBackError};
+
+fn debugger(program: &str, input: &str) -> Debugger<MemoryIo> {
+    let program: Program = program.parse().unwrap();
+    Debugger::new(State::with_io(program, MemoryIo::new(input.as_bytes())))
+}
+
+#[test]
+fn commands() {
+    assert_eq!(Command::parse("step"), Ok(Command::Step(1)));
+    assert_eq!(Command::parse("s 12"), Ok(Command::Step(12)));
+    assert_eq!(Command::parse("continue"), Ok(Command::Continue));
+    assert_eq!(
+        Command::parse("break 2 5"),
+        Ok(Command::Break(Breakpoint::Location((4, 1))))
+    );
+    assert_eq!(
+        Command::parse("b w"),
+        Ok(Command::Break(Breakpoint::Instruction('w')))
+    );
+    assert_eq!(Command::parse("d 0"), Ok(Command::Delete(0)));
+    assert!(Command::parse("break 0 1").is_err());
+    assert!(Command::parse("step many").is_err());
+    assert!(Command::parse("jump").is_err());
+}
+
+#[test]
+fn short_forms() {
+    for (short, long) in &[
+        ("s", "step"),
+        ("c", "continue"),
+        ("rs", "back"),
+        ("rc", "reverse-continue"),
+        ("b 1 2", "break 1 2"),
+        ("b if reverse", "break if reverse"),
+        ("w", "watch"),
+        ("d 1", "delete 1"),
+        ("p", "print"),
+        ("g", "grid"),
+        ("h", "help"),
+        ("q", "quit"),
+    ] {
+        assert_eq!(Command::parse(short), Command::parse(long), "{}", short);
+    }
+    assert!(Command::parse("breakpoints").is_ok());
+    assert!(Command::parse("bp").is_err());
+}
+
+#[test]
+fn breakpoints() {
+    let mut debugger = debugger("@\"iH\"ww", "");
+    debugger.add_breakpoint(Breakpoint::Instruction('w'));
+    debugger.add_breakpoint(Breakpoint::Location((2, 0)));
+
+    assert_eq!(debugger.continue_execution(), Stop::Breakpoint(1));
+    assert_eq!(debugger.steps(), 1);
+    assert_eq!(debugger.continue_execution(), Stop::Breakpoint(0));
+    assert_eq!(debugger.state.data_stack.to_string(), "105:72");
+    assert_eq!(debugger.step(5), Stop::Breakpoint(0));
+    assert_eq!(debugger.state.io.output(), "H");
+
+    debugger.remove_breakpoint(0);
+    assert_eq!(debugger.continue_execution(), Stop::Halted);
+    assert_eq!(debugger.state.io.output(), "Hi");
+    assert_eq!(debugger.step(1), Stop::Halted);
+}
+
+#[test]
+fn breakpoint_on_the_last_step() {
+    let mut debugger = debugger("@\"iH\"ww", "");
+    debugger.add_breakpoint(Breakpoint::Location((2, 0)));
+
+    assert_eq!(debugger.step(1), Stop::Breakpoint(0));
+    assert_eq!(debugger.step(2), Stop::Done);
+    assert_eq!(debugger.back(2), Stop::Breakpoint(0));
+    assert_eq!(debugger.steps(), 1);
+}
+
+#[test]
+fn stuck() {
+    let mut debugger = debugger("@)", "");
+    match debugger.step(10) {
+        Stop::Stuck(_) => {}
+        stop => panic!("expected the program to get stuck, got {:?}", stop),
+    }
+    assert_eq!(debugger.steps(), 0);
+}
+
+#[test]
+fn repl() {
+    let mut debugger = debugger("@\"iH\"ww", "");
+    let mut output = Vec::new();
+    debugger
+        .repl("b w\nc\n\nbogus\nc\nq\ns\n".as_bytes(), &mut output)
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.contains("breakpoint 0 at instruction `w`\n"));
+    assert!(output.contains("unknown command `bogus`"));
+    assert!(output.contains("program halted after 6 steps"));
+    assert_eq!(debugger.state.io.output(), "Hi");
+}
+
+#[test]
+fn back() {
+    let mut debugger = debugger("@\"iH\"ww", "");
+    assert_eq!(debugger.step(4), Stop::Done);
+    assert_eq!(debugger.state.data_stack.to_string(), "105:72");
+
+    assert_eq!(debugger.back(2), Stop::Done);
+    assert_eq!(debugger.steps(), 2);
+    assert_eq!(debugger.state.data_stack.to_string(), "105");
+    assert_eq!(debugger.state.location, (3, 0));
+    assert!(debugger.state.string_mode);
+
+    assert_eq!(debugger.continue_execution(), Stop::Halted);
+    assert_eq!(debugger.state.io.output(), "Hi");
+
+    // undoing the writes takes the characters off the output stack, what was written stays written
+    debugger.add_breakpoint(Breakpoint::Location((4, 0)));
+    assert_eq!(debugger.reverse_continue(), Stop::Breakpoint(0));
+    assert_eq!(debugger.state.data_stack.to_string(), "105:72");
+    assert!(debugger.state.output_stack.is_empty());
+    assert_eq!(debugger.state.io.output(), "Hi");
+    assert_eq!(
+        debugger.reverse_continue(),
+        Stop::StepBack(StepBackError::AtStart)
+    );
+    assert_eq!(debugger.steps(), 0);
+    assert!(debugger.state.data_stack.is_empty());
+    assert!(!debugger.state.reverse_mode);
+}
+
+#[test]
+fn back_commands() {
+    assert_eq!(Command::parse("back"), Ok(Command::Back(1)));
+    assert_eq!(Command::parse("reverse-step 3"), Ok(Command::Back(3)));
+    assert_eq!(Command::parse("rc"), Ok(Command::ReverseContinue));
+}
+
+#[test]
+fn conditions() {
+    let mut state = State::with_io("@\"iH\"ww".parse().unwrap(), MemoryIo::default());
+    let holds = |state: &State<MemoryIo>, condition: &str| {
+        Condition::parse(condition).unwrap().holds(state)
+    };
+    assert!(!holds(&state, "data[0] == 0"));
+    assert!(holds(&state, "control.len == 0 && !reverse"));
+    assert!(holds(&state, "line == 1 && col == 2 && direction == east"));
+
+    for _ in 0..3 {
+        state.step().unwrap();
+    }
+    assert!(holds(&state, "data[0] == 72 && data[1] == 105"));
+    assert!(holds(&state, "data.len > 1 && string"));
+    assert!(holds(&state, "data[0] < -1 || (data[0] >= 72)"));
+    assert!(!holds(&state, "data[2] != 0"));
+
+    for condition in &[
+        "data.len",
+        "data[0] == east",
+        "direction < west",
+        "data[0] <",
+        "(reverse",
+        "foo",
+        "data[-1] == 0",
+        "reverse true",
+        "1 && reverse",
+        "reverse && data[0]",
+        "data.len || string",
+        "string || 2",
+    ] {
+        assert!(Condition::parse(condition).is_err(), "{}", condition);
+    }
+}
+
+#[test]
+fn conditional_breakpoints() {
+    assert_eq!(
+        Command::parse("break if data.len >= 2"),
+        Ok(Command::Break(Breakpoint::Condition(
+            Condition::parse("data.len >= 2").unwrap()
+        )))
+    );
+    assert_eq!(
+        Command::parse("watch 3"),
+        Ok(Command::Break(Breakpoint::Watch(3)))
+    );
+    assert!(Command::parse("break if data.len").is_err());
+
+    let mut debugger = debugger("@\"iH\"ww", "");
+    debugger.add_breakpoint(Breakpoint::Condition(
+        Condition::parse("!string && data.len == 2").unwrap(),
+    ));
+    // holds all the time, so it never becomes true
+    debugger.add_breakpoint(Breakpoint::Condition(
+        Condition::parse("direction == east").unwrap(),
+    ));
+    assert_eq!(debugger.continue_execution(), Stop::Breakpoint(0));
+    assert_eq!(debugger.steps(), 4);
+    assert_eq!(debugger.continue_execution(), Stop::Halted);
+
+    // stepping backwards makes it true again
+    assert_eq!(debugger.reverse_continue(), Stop::Breakpoint(0));
+    assert_eq!(debugger.steps(), 4);
+}
+
+#[test]
+fn watchpoints() {
+    let mut debugger = debugger("@\"iH\"ww", "");
+    debugger.add_breakpoint(Breakpoint::Watch(1));
+
+    assert_eq!(debugger.continue_execution(), Stop::Breakpoint(0));
+    assert_eq!(debugger.steps(), 2);
+    assert_eq!(debugger.continue_execution(), Stop::Breakpoint(0));
+    assert_eq!(debugger.steps(), 3);
+    assert_eq!(debugger.continue_execution(), Stop::Breakpoint(0));
+    assert_eq!(debugger.steps(), 5);
+    assert_eq!(debugger.state.io.output(), "H");
+}
+
+#[test]
+fn back_at_the_start_without_a_start_symbol() {
+    let mut debugger = debugger("( )", "");
+    assert_eq!(debugger.back(1), Stop::StepBack(StepBackError::AtStart));
+    assert_eq!(debugger.steps(), 0);
+
+    assert_eq!(debugger.step(2), Stop::Done);
+    assert_eq!(debugger.back(2), Stop::Done);
+    assert_eq!(debugger.back(1), Stop::StepBack(StepBackError::AtStart));
+    assert_eq!(debugger.steps(), 0);
+}
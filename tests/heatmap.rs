@@ -0,0 +1,43 @@
+extern crate befreak;
+
+use befreak::interpreter::{self, Limits};
+use befreak::{heatmap, MemoryIo, Program, State, Statistics};
+
+fn run(program: &str) -> Statistics {
+    let program: Program = program.parse().unwrap();
+    let mut state: State<_> = State::with_io(program, MemoryIo::default());
+    let (result, statistics) =
+        interpreter::run_with_statistics(&mut state, &mut (), &Limits::default());
+    result.unwrap();
+    statistics
+}
+
+#[test]
+fn ansi() {
+    // the second line is never reached
+    let map = heatmap::ansi(&run("@\"iH\"ww\n<  >"));
+    let lines: Vec<&str> = map.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("\x1b[30;48;5;"));
+    assert_eq!(
+        lines[1],
+        "\x1b[38;5;240m<\x1b[0m  \x1b[38;5;240m>\x1b[0m   "
+    );
+    assert_eq!(
+        lines[2],
+        "executed 1 to 1 times from blue to red, never executed in grey"
+    );
+}
+
+#[test]
+fn svg() {
+    let map = heatmap::svg(&run("@\"<&\"ww\n>  "));
+    assert!(map.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"84\" height=\"40\""));
+    assert!(map.ends_with("</svg>\n"));
+    assert!(map.contains("<title>line 1, col 1: executed once</title>"));
+    assert!(map.contains(">&lt;</text>"));
+    assert!(map.contains(">&amp;</text>"));
+    assert!(map.contains("<title>line 2, col 1: never executed</title>"));
+    // unreached spaces are left out
+    assert!(!map.contains("line 2, col 2:"));
+}
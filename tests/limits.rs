@@ -0,0 +1,72 @@
+extern crate befreak;
+
+use std::time::Duration;
+
+use befreak::interpreter::{self, Limits, Outcome};
+use befreak::{MemoryIo, Program, State};
+
+fn run(program: &str, limits: &Limits) -> Outcome {
+    let program: Program = program.parse().unwrap();
+    let mut state: State<_> = State::with_io(program, MemoryIo::default());
+    interpreter::run_with_limits(&mut state, &mut (), limits).unwrap()
+}
+
+#[test]
+fn halting() {
+    let limits = Limits {
+        max_steps: Some(100),
+        detect_cycles: true,
+        ..Limits::default()
+    };
+    assert_eq!(run("@\"iH\"ww", &limits), Outcome::Halted { steps: 6 });
+}
+
+#[test]
+fn step_limit() {
+    let limits = Limits {
+        max_steps: Some(1000),
+        ..Limits::default()
+    };
+    assert_eq!(run("()", &limits), Outcome::StepLimit { steps: 1000 });
+    assert_eq!(run("@\"iH\"ww", &limits), Outcome::Halted { steps: 6 });
+}
+
+#[test]
+fn timeout() {
+    let limits = Limits {
+        timeout: Some(Duration::from_millis(50)),
+        ..Limits::default()
+    };
+    match run("()", &limits) {
+        Outcome::Timeout { .. } => {}
+        outcome => panic!("expected a timeout, got {:?}", outcome),
+    }
+}
+
+#[test]
+fn cycles() {
+    let limits = Limits {
+        detect_cycles: true,
+        ..Limits::default()
+    };
+    assert_eq!(
+        run("()", &limits),
+        Outcome::Cycle {
+            steps: 4,
+            length: 2
+        }
+    );
+
+    match run("(  )  ", &limits) {
+        Outcome::Cycle { length, .. } => assert_eq!(length, 6),
+        outcome => panic!("expected a cycle, got {:?}", outcome),
+    }
+
+    // the stack grows forever, so no state repeats
+    let limits = Limits {
+        max_steps: Some(10_000),
+        detect_cycles: true,
+        ..Limits::default()
+    };
+    assert_eq!(run("(1", &limits), Outcome::StepLimit { steps: 10_000 });
+}
@@ -0,0 +1,61 @@
+extern crate befreak;
+
+use befreak::reachability::Reachability;
+use befreak::{Direction, Program};
+
+fn analyse(program: &str) -> Reachability {
+    let program: Program = program.parse().unwrap();
+    Reachability::analyse(&program)
+}
+
+#[test]
+fn straight_line() {
+    let r = analyse("@\"iH\"ww");
+    assert!(r.unreachable().is_empty());
+    assert!(r.stuck().is_empty());
+    assert!(r.can_halt());
+    assert!(r.is_reachable((0, 0)));
+    assert_eq!(r.directions((1, 0)), vec![Direction::East]);
+}
+
+#[test]
+fn dead_code() {
+    let r = analyse("@(j)");
+    assert_eq!(r.unreachable(), &[(3, 0)]);
+    assert_eq!(r.stuck().len(), 1);
+    assert_eq!(r.stuck()[0].location, (2, 0));
+    assert!(!r.can_halt());
+    assert!(!r.is_reachable((3, 0)));
+    assert!(r.directions((3, 0)).is_empty());
+}
+
+#[test]
+fn branches() {
+    // `<` entered from the opposite side may turn either way, both ways lead back to it from the
+    // side and on to the start symbol, the last column is never reached
+    let r = analyse("  w\n@< \n  w");
+    assert_eq!(r.unreachable(), &[(2, 0), (2, 2)]);
+    assert!(r.can_halt());
+    assert_eq!(
+        r.directions((1, 1)),
+        vec![Direction::North, Direction::East, Direction::South]
+    );
+    assert_eq!(
+        r.directions((1, 0)),
+        vec![Direction::North, Direction::South]
+    );
+}
+
+#[test]
+fn reverse_mode() {
+    // `>` entered from behind turns around in reverse mode, the start symbol is then stuck
+    let r = analyse("@> ");
+    assert!(!r.can_halt());
+    assert_eq!(r.stuck().len(), 1);
+    assert_eq!(r.stuck()[0].location, (0, 0));
+    assert!(r.stuck()[0].reverse_mode);
+    // in string mode mirrors and branches are characters like any other
+    let r = analyse("@\"/\\<\"ww(");
+    assert!(r.can_halt());
+    assert!(r.unreachable().is_empty());
+}
@@ -0,0 +1,68 @@
+extern crate befreak;
+
+use befreak::interpreter::{self, Limits, Outcome};
+use befreak::{MemoryIo, Program, Rule, State, Statistics};
+
+fn run(program: &str) -> Statistics {
+    let program: Program = program.parse().unwrap();
+    let mut state: State<_> = State::with_io(program, MemoryIo::default());
+    let (result, statistics) =
+        interpreter::run_with_statistics(&mut state, &mut (), &Limits::default());
+    assert_eq!(
+        result.unwrap(),
+        Outcome::Halted {
+            steps: statistics.steps
+        }
+    );
+    statistics
+}
+
+#[test]
+fn counts() {
+    let statistics = run("@\"iH\"ww");
+    assert_eq!(statistics.steps, 6);
+    assert_eq!(statistics.forward_steps(), 6);
+    assert_eq!(statistics.reverse_steps, 0);
+    assert_eq!(statistics.rules.len(), 3);
+    assert_eq!(statistics.rules[&Rule::StringToggle], 2);
+    assert_eq!(statistics.rules[&Rule::StringPush], 2);
+    assert_eq!(statistics.rules[&Rule::Write], 2);
+    // the halt
+    assert_eq!(statistics.hits((0, 0)), 1);
+    assert_eq!(statistics.hits((1, 0)), 1);
+    assert_eq!(statistics.hits((6, 0)), 1);
+    assert_eq!(statistics.hits((7, 0)), 0);
+    assert_eq!(statistics.max_data_depth, 2);
+    assert_eq!(statistics.max_control_depth, 0);
+    assert_eq!(statistics.branch_decisions, 0);
+}
+
+#[test]
+fn reverse_mode() {
+    // `(` pushes, `?` enters reverse mode where `)` pushes too, the second `?` leaves it again
+    let statistics = run("@(?)?");
+    assert_eq!(statistics.steps, 4);
+    assert_eq!(statistics.reverse_steps, 2);
+    assert_eq!(statistics.rules[&Rule::Reverse], 2);
+    assert_eq!(statistics.rules[&Rule::Push], 2);
+    assert_eq!(statistics.max_data_depth, 2);
+}
+
+#[test]
+fn stuck() {
+    let program: Program = "@w".parse().unwrap();
+    let mut state: State<_> = State::with_io(program, MemoryIo::default());
+    let (result, statistics) =
+        interpreter::run_with_statistics(&mut state, &mut (), &Limits::default());
+    assert!(result.is_err());
+    assert_eq!(statistics.steps, 0);
+    assert!(statistics.rules.is_empty());
+}
+
+#[test]
+fn report() {
+    let report = run("@\"iH\"ww").to_string();
+    assert!(report.starts_with("steps:            6\nforward steps:    6\nreverse steps:    0\n"));
+    assert!(report.contains("  write                   2  "));
+    assert!(report.contains("  line   1, col   2 `\"`          1\n"));
+}
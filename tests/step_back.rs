@@ -0,0 +1,90 @@
+extern crate befreak;
+
+use befreak::{MemoryIo, Program, State, StepBackError};
+
+fn state(program: &str, input: &str) -> State<MemoryIo> {
+    let program: Program = program.parse().unwrap();
+    State::with_io(program, MemoryIo::new(input.as_bytes()))
+}
+
+/// Strings, `r`, `w`, mirrors and all three branch rules, without numbers. Runs for 24 steps
+/// until it tries to pop the wrong character of the string in reverse mode.
+const PROGRAM: &str = "@\"ab\"r\\\n      w\n   /  <\n <[^   ";
+
+#[test]
+fn step_back_undoes_every_step() {
+    let mut s = state(PROGRAM, "x");
+    let mut history = vec![s.to_string()];
+    while s.step().is_ok() {
+        history.push(s.to_string());
+    }
+    assert_eq!(history.len(), 25);
+
+    history.pop();
+    while let Some(previous) = history.pop() {
+        s.step_back().unwrap();
+        assert_eq!(s.to_string(), previous);
+    }
+    assert_eq!(s.step_back(), Err(StepBackError::AtStart));
+    assert_eq!(s.to_string(), state(PROGRAM, "x").to_string());
+}
+
+#[test]
+fn step_back_refuses_numbers() {
+    let mut s = state("@(12)", "");
+    s.step().unwrap();
+    s.step().unwrap();
+    assert_eq!(s.step_back(), Err(StepBackError::InsideNumber));
+    s.step().unwrap();
+    // digit_end
+    s.step().unwrap();
+    assert_eq!(s.step_back(), Err(StepBackError::AfterNumber));
+}
+
+#[test]
+fn step_back_reports_stuck_inverses() {
+    let mut s = state("@(", "");
+    s.step().unwrap();
+    // `)` can not pop anything but the zero pushed by `(`
+    s.data_stack.pop();
+    s.data_stack.push(1);
+    match s.step_back() {
+        Err(StepBackError::Stuck(_)) => {}
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert_eq!(s.location, (0, 0));
+}
+
+#[test]
+fn step_back_stops_at_the_start_without_a_start_symbol() {
+    // the program starts at the top left and wraps around to it
+    let mut s = state("( )", "");
+    for _ in 0..3 {
+        s.step().unwrap();
+    }
+    assert_eq!(s.location, (0, 0));
+    assert_eq!(s.step_back(), Err(StepBackError::AtStart));
+
+    let mut s = state("( )", "");
+    s.step().unwrap();
+    s.step().unwrap();
+    s.step_back().unwrap();
+    s.step_back().unwrap();
+    assert_eq!(s.step_back(), Err(StepBackError::AtStart));
+    assert_eq!(s.to_string(), state("( )", "").to_string());
+}
+
+#[test]
+fn step_back_over_the_start_symbol_in_a_string() {
+    // the string wraps around and pushes the start symbol
+    let mut s = state("@\"ab", "");
+    for _ in 0..5 {
+        s.step().unwrap();
+    }
+    assert_eq!(s.data_stack.to_string(), "97:98:64");
+    for _ in 0..5 {
+        s.step_back().unwrap();
+    }
+    assert_eq!(s.step_back(), Err(StepBackError::AtStart));
+    assert_eq!(s.to_string(), state("@\"ab", "").to_string());
+}
@@ -0,0 +1,50 @@
+extern crate befreak;
+
+use befreak::{interpreter, JsonTrace, MemoryIo, Program, State};
+
+#[test]
+fn json_trace() {
+    let program: Program = "@\"iH\"wr(".parse().unwrap();
+    let mut state: State<_> = State::with_io(program, MemoryIo::new(b"q"));
+    let mut trace = Vec::new();
+    interpreter::run(&mut state, &mut JsonTrace::new(&mut trace)).unwrap();
+
+    let trace = String::from_utf8(trace).unwrap();
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines.len(), 8);
+    assert_eq!(
+        lines[0],
+        "{\"step\":0,\"instruction\":\"\\\"\",\"line\":1,\"column\":2,\"direction\":\"east\",\
+         \"data\":[],\"control\":[],\"reverse_mode\":false,\"string_mode\":false,\
+         \"accumulator\":\"\",\"rule\":\"string_toggle\"}"
+    );
+    assert!(lines[4].ends_with("\"data\":[105,72],\"control\":[],\"reverse_mode\":false,\"string_mode\":false,\"accumulator\":\"\",\"rule\":\"write\",\"written\":\"H\"}"));
+    assert!(lines[5].ends_with("\"rule\":\"read\",\"read\":\"q\"}"));
+    assert!(lines[7].ends_with("\"data\":[105,113,0],\"control\":[],\"reverse_mode\":false,\"string_mode\":false,\"accumulator\":\"\",\"rule\":\"halt\"}"));
+}
+
+#[test]
+fn json_trace_reports_undone_io() {
+    // after undoing the write and the read it gets stuck on the start symbol in reverse mode
+    let program: Program = "@rw?wr".parse().unwrap();
+    let mut state: State<_> = State::with_io(program, MemoryIo::new(b"q"));
+    let mut trace = Vec::new();
+    assert!(interpreter::run(&mut state, &mut JsonTrace::new(&mut trace)).is_err());
+
+    let trace = String::from_utf8(trace).unwrap();
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines.len(), 6, "{}", trace);
+    assert!(lines[3].ends_with("\"rule\":\"unwrite\",\"unwritten\":\"q\"}"));
+    assert!(lines[4].ends_with("\"rule\":\"unread\",\"unread\":\"q\"}"));
+}
+
+#[test]
+fn json_trace_reports_errors() {
+    let program: Program = "@)".parse().unwrap();
+    let mut state: State<_> = State::with_io(program, MemoryIo::default());
+    let mut trace = Vec::new();
+    assert!(interpreter::run(&mut state, &mut JsonTrace::new(&mut trace)).is_err());
+
+    let trace = String::from_utf8(trace).unwrap();
+    assert!(trace.contains(",\"error\":\"stack underflow"), "{}", trace);
+}
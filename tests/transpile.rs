@@ -0,0 +1,289 @@
+//! Differential tests: a program translated to Rust must behave like the interpreter, with the
+//! same output, error message and exit status.
+
+extern crate befreak;
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use befreak::interpreter::{self, Limits, Outcome};
+use befreak::pointer::Pointer;
+use befreak::transpile::{self, Action, CellType, StateMachine};
+use befreak::{Cell, MemoryIo, Program, State};
+
+/// What a run prints and how it exits.
+#[derive(Debug, PartialEq)]
+struct Run {
+    stdout: String,
+    stderr: String,
+    status: i32,
+}
+
+/// Run `source` with the interpreter.
+fn interpret<C: Cell>(source: &str, input: &str, max_steps: usize) -> Run {
+    let program: Program = source.parse().unwrap();
+    let mut state: State<_, C> = State::with_io(program, MemoryIo::new(input.as_bytes()));
+    let limits = Limits {
+        max_steps: Some(max_steps),
+        ..Limits::default()
+    };
+    let result = interpreter::run_with_limits(&mut state, &mut (), &limits);
+    let (stderr, status) = match result {
+        Ok(Outcome::Halted { .. }) => (String::new(), 0),
+        Ok(outcome) => (format!("error: {}\n", outcome), 2),
+        Err(e) => (format!("error: {}\n", e), 1),
+    };
+    Run {
+        stdout: state.io.output().to_string(),
+        stderr,
+        status,
+    }
+}
+
+/// A language programs are translated to.
+#[derive(Clone, Copy, Debug)]
+enum Target {
+    Rust,
+    C,
+    /// C without the runtime's `main` and stdio, with small stacks, see `EMBEDDED`.
+    EmbeddedC,
+}
+
+const TARGETS: [Target; 3] = [Target::Rust, Target::C, Target::EmbeddedC];
+
+/// A program around a C translation, `{}`, like a program for a microcontroller would be. The
+/// hooks of the runtime are defined before the translation and only use stdio after it, the
+/// translation must not have included stdio itself.
+const EMBEDDED: &str = r#"
+#include <stdlib.h>
+
+static int embedded_getchar(void);
+static int embedded_putchar(int c);
+static void embedded_fail(int status, const char *message);
+
+#define BEFREAK_NO_MAIN
+#define BEFREAK_STACK_SIZE 1024
+#define BEFREAK_GETCHAR() embedded_getchar()
+#define BEFREAK_PUTCHAR(c) embedded_putchar(c)
+#define BEFREAK_FAIL(status, message) embedded_fail(status, message)
+
+{}
+
+#ifdef EOF
+#error "the runtime included stdio"
+#endif
+
+#include <stdio.h>
+
+static int embedded_getchar(void)
+{
+    return getchar();
+}
+
+static int embedded_putchar(int c)
+{
+    return putchar(c);
+}
+
+static void embedded_fail(int status, const char *message)
+{
+    fflush(stdout);
+    fprintf(stderr, "error: %s\n", message);
+    exit(status);
+}
+
+int main(int argc, char **argv)
+{
+    if (argc == 3) {
+        befreak_machine.max_steps = strtoull(argv[2], NULL, 10);
+    }
+    befreak_run(&befreak_machine);
+    return 0;
+}
+"#;
+
+/// Translate `source` to `target`, compile it without warnings and run it.
+fn compile_and_run(
+    name: &str,
+    source: &str,
+    target: Target,
+    cell: CellType,
+    input: &str,
+    max_steps: usize,
+) -> Run {
+    let program: Program = source.parse().unwrap();
+    let directory = env::temp_dir().join(format!("befreak-transpile-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let binary: PathBuf = directory.join(format!("{}-{:?}", name, target));
+    let (translation, mut compiler) = match target {
+        Target::Rust => {
+            let translation = directory.join(format!("{}.rs", name));
+            fs::write(&translation, transpile::rust(&program, cell)).unwrap();
+            let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+            (translation, Command::new(rustc))
+        }
+        Target::C | Target::EmbeddedC => {
+            let translation = directory.join(format!("{}-{:?}.c", name, target));
+            let mut source = transpile::c(&program, cell);
+            if let Target::EmbeddedC = target {
+                source = EMBEDDED.replace("{}", &source);
+            }
+            fs::write(&translation, source).unwrap();
+            let mut cc = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_string()));
+            cc.args(["-std=c99", "-pedantic", "-Wall", "-Wextra"]);
+            (translation, cc)
+        }
+    };
+    let compiled = compiler
+        .arg(&translation)
+        .arg("-o")
+        .arg(&binary)
+        .output()
+        .unwrap();
+    let warnings = String::from_utf8_lossy(&compiled.stderr);
+    assert!(
+        compiled.status.success(),
+        "{} {:?}: {}",
+        name,
+        target,
+        warnings
+    );
+    assert!(warnings.is_empty(), "{} {:?}: {}", name, target, warnings);
+
+    let mut child = Command::new(&binary)
+        .arg("--max-steps")
+        .arg(max_steps.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    fs::remove_file(&translation).unwrap();
+    fs::remove_file(&binary).unwrap();
+    // other tests may still be using the directory
+    let _ = fs::remove_dir(&directory);
+    Run {
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+        status: output.status.code().unwrap(),
+    }
+}
+
+fn assert_same(name: &str, source: &str, input: &str, max_steps: usize) {
+    let expected = interpret::<i32>(source, input, max_steps);
+    for &target in &TARGETS {
+        let actual = compile_and_run(name, source, target, CellType::I32, input, max_steps);
+        assert_eq!(actual, expected, "{} {:?}", name, target);
+    }
+}
+
+#[test]
+fn examples() {
+    let examples = [
+        ("hello", include_str!("../programs/hello"), 100_000),
+        ("hello2", include_str!("../programs/hello2"), 100_000),
+        ("hello3", include_str!("../programs/hello3"), 100_000),
+        ("primes", include_str!("../programs/primes"), 200_000),
+        ("primes2", include_str!("../programs/primes2"), 200_000),
+        ("primes3", include_str!("../programs/primes3"), 200_000),
+    ];
+    for &(name, source, max_steps) in &examples {
+        assert_same(name, source, "", max_steps);
+    }
+}
+
+#[test]
+fn wide_cells() {
+    // 100000 * 100000 overflows an i32 but not an i64
+    let source = "@(100000((100000*sw";
+    let expected = interpret::<i64>(source, "", 1000);
+    assert!(expected.stderr.contains("10000000000"), "{:?}", expected);
+    for &target in &TARGETS {
+        let actual = compile_and_run("wide_cells", source, target, CellType::I64, "", 1000);
+        assert_eq!(actual, expected, "{:?}", target);
+    }
+}
+
+#[test]
+fn step_limit() {
+    assert_same("step_limit", include_str!("../programs/hello"), "", 25);
+}
+
+#[test]
+fn errors() {
+    let programs = [
+        ("underflow", "@)"),
+        ("control_underflow", "@!"),
+        ("pop_nonzero", "@(1)"),
+        ("division_by_zero", "@(1(%"),
+        ("unknown_instruction", "@x"),
+        ("non_ascii", "@(200w"),
+        ("unread_control", "@(10?r?r"),
+        ("digit_end_underflow", "@12"),
+        ("start_in_reverse", "@(65w?"),
+        ("output_underflow", "@?w"),
+        ("string_mismatch", "@(?\"a\""),
+        ("string_backslash", "@(?\"\\\""),
+        ("string_trigraph", "@(?\"??=\""),
+    ];
+    for &(name, source) in &programs {
+        assert_same(name, source, "", 1000);
+    }
+}
+
+#[test]
+fn operations() {
+    // the stacks start as 7, -4, 12 and 1, popping then fails with the first, second or third
+    // element of the data stack
+    for c in "[]$'`+-%*~&|#{}!=lgsdbcfou:;".chars() {
+        for (i, shown) in [")", "s)", "d)"].iter().enumerate() {
+            let source = format!("@(7(3~(12(1[{}{}", c, shown);
+            let name = format!("operation_{}_{}", c as u32, i);
+            assert_same(&name, &source, "", 1000);
+        }
+    }
+}
+
+#[test]
+fn input() {
+    assert_same("echo", "@rw", "A", 1000);
+    assert_same("input_exhausted", "@rw", "", 1000);
+    assert_same("unprintable", "@rw", "\n", 1000);
+}
+
+#[test]
+fn state_machine() {
+    let program: Program = "@(1)".parse().unwrap();
+    let machine = StateMachine::new(&program);
+    // the start is the first state, every reachable pointer has one state
+    let start = Pointer::start(&program);
+    assert_eq!(machine.nodes[0].pointer, start);
+    assert_eq!(machine.nodes.len(), 4);
+    let next = &machine.nodes[machine.nodes[0].next[0]];
+    assert_eq!(next.pointer, start.advance(&program));
+    assert_eq!(next.action, Action::Digit(1));
+}
+
+#[test]
+fn output_is_only_recorded_to_unwrite_it() {
+    let machine = |source: &str| StateMachine::new(&source.parse().unwrap());
+    assert!(!machine("@(65w").unwrites());
+    assert!(machine("@(65w?w").unwrites());
+}
+
+#[test]
+fn long_output() {
+    // more characters than the stacks of the C translations hold, none of them is unwritten
+    let source = format!("@(65{}", ":w".repeat(5000));
+    assert_same("long_output", &source, "", 20_000);
+}
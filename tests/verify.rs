@@ -0,0 +1,59 @@
+extern crate befreak;
+
+use std::fs;
+
+use befreak::interpreter::{Limits, Outcome};
+use befreak::verify::{verify, verify_with_limits, VerificationError};
+use befreak::Program;
+
+fn program(path: &str) -> Program {
+    fs::read_to_string(path).unwrap().parse().unwrap()
+}
+
+#[test]
+fn hello_programs_are_reversible() {
+    for path in &["programs/hello", "programs/hello2", "programs/hello3"] {
+        let verified = verify::<i32>(program(path), b"").unwrap();
+        assert_eq!(verified.output, "Hello world!\n", "{}", path);
+    }
+}
+
+#[test]
+fn input_is_restored() {
+    let program: Program = "@rr\"ab\"ww".parse().unwrap();
+    let verified = verify::<i32>(program, b"xy").unwrap();
+    assert_eq!(verified.output, "ba");
+}
+
+#[test]
+fn stuck_programs_are_reported() {
+    let program: Program = "@(1)".parse().unwrap();
+    match verify::<i32>(program, b"") {
+        Err(VerificationError::Forward(_)) => {}
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn irreversible_programs_are_reported() {
+    // the branch turns south on the 2 on the control stack, but going back it pushes a 1
+    let program: Program = "@(2[<\n/   \\".parse().unwrap();
+    match verify::<i32>(program, b"") {
+        Err(VerificationError::Diverged { step: 3, .. }) => {}
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn long_runs_are_stopped() {
+    let limits = Limits {
+        max_steps: Some(1000),
+        ..Limits::default()
+    };
+    match verify_with_limits::<i32>(program("programs/primes"), b"", &limits) {
+        Err(VerificationError::Stopped(Outcome::StepLimit { steps: 1000 })) => {}
+        result => panic!("unexpected result {:?}", result),
+    }
+    let verified = verify_with_limits::<i32>(program("programs/hello"), b"", &limits).unwrap();
+    assert_eq!(verified.output, "Hello world!\n");
+}